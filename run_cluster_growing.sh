@@ -0,0 +1,8 @@
+cargo build --release
+# initial floorplans of the cluster growing order on n300 for both start nodes, the time of the ordering is logged
+echo "instance,floorplan,alpha,time[ms],total_area,dead_area,total_wire,iterations,cluster_growing,recursive_bisection" > eval/cluster_growing.csv
+for start_node in 0 most_connected; do
+    ./target/release/floorplanning --input benchmark/n300.floor -f sequence_pair -i 1 -a 0.5 -r -c --start-node $start_node >> eval/cluster_growing.csv 2>> eval/cluster_growing.log
+    ./target/release/floorplanning --input benchmark/n300.floor -f slicing_tree  -i 1 -a 0.5 -r -c --start-node $start_node >> eval/cluster_growing.csv 2>> eval/cluster_growing.log
+done
+grep "cluster growing order" eval/cluster_growing.log
@@ -0,0 +1,15 @@
+cargo build --release
+# area gap to the optimum on generated dissections without dead area, alpha 1 optimizes the area only
+mkdir -p eval/optimum_gap
+echo "instance,floorplan,alpha,time[ms],total_area,optimal_area,area_gap[%],total_wire,iterations" > eval/optimum_gap.csv
+for dissection in guillotine non_guillotine; do
+    for n in 50 100; do
+        for seed in 1 2 3; do
+            instance=eval/optimum_gap/${dissection}_${n}_${seed}.floor
+            ./target/release/floorplanning generate --output $instance --modules $n --nets $n --seed $seed --dissection $dissection --locality 0.8
+            for floorplan in slicing_tree sequence_pair b_star_tree; do
+                ./target/release/floorplanning --input $instance -f $floorplan -a 1.0 -i 1000000 --benchmark >> eval/optimum_gap.csv 2>> eval/optimum_gap.log
+            done
+        done
+    done
+done
@@ -0,0 +1,135 @@
+use crate::definitions::*;
+use crate::floorplan_common::split_region;
+use crate::hypergraph::Hypergraph;
+use rand::prelude::*;
+
+// rounds of solving the quadratic placement and spreading the modules
+const ROUNDS: usize = 30;
+// Gauss-Seidel sweeps of each round
+const SWEEPS: usize = 20;
+
+// positions along one axis such that the modules sorted by their coordinate fill the axis uniformly by area
+fn spread(coordinates: &[f64], areas: &[f64], length: f64) -> Vec<f64> {
+    let total: f64 = areas.iter().sum();
+    let mut order: Vec<usize> = (0..coordinates.len()).collect();
+    order.sort_by(|&a, &b| coordinates[a].total_cmp(&coordinates[b]));
+    let mut target: Vec<f64> = vec![0.0; coordinates.len()];
+    let mut filled = 0.0;
+    for i in order {
+        target[i] = (filled + areas[i] / 2.0) / total * length;
+        filled += areas[i];
+    }
+    target
+}
+
+/// force-directed placement of the module centers inside a square of the module area:
+/// the quadratic wirelength of the clique net model is minimized with anchors pulling each module to a position
+/// that spreads the modules evenly, the anchors get stronger in each round, the start positions are drawn from rng
+pub fn force_directed_placement(graph: &Hypergraph, modules: &[Rectangle], rng: &mut StdRng) -> Vec<(f64, f64)> {
+    let n = modules.len();
+    let areas: Vec<f64> = modules.iter().map(|rect| rect.area() as f64).collect();
+    let side = areas.iter().sum::<f64>().sqrt();
+
+    // clique net model, a net with k pins has edges of weight 1 / (k - 1)
+    let mut neighbors: Vec<Vec<(usize, f64)>> = vec![Vec::new(); n];
+    for e in 0..graph.num_nets {
+        let pins = graph.pins(e);
+        let weight = 1.0 / (pins.len() as f64 - 1.0);
+        for &u in pins.iter() {
+            for &v in pins.iter().filter(|&&v| v != u) {
+                neighbors[u].push((v, weight));
+            }
+        }
+    }
+    let degree: Vec<f64> = neighbors.iter().map(|edges| edges.iter().map(|(_, w)| w).sum()).collect();
+    let average_degree = (degree.iter().sum::<f64>() / n.max(1) as f64).max(1.0);
+
+    let mut x: Vec<f64> = (0..n).map(|_| rng.gen_range(0.0..side)).collect();
+    let mut y: Vec<f64> = (0..n).map(|_| rng.gen_range(0.0..side)).collect();
+    for round in 0..ROUNDS {
+        let anchor_x = spread(&x, &areas, side);
+        let anchor_y = spread(&y, &areas, side);
+        let anchor_weight = average_degree * (round + 1) as f64 / ROUNDS as f64;
+        for _ in 0..SWEEPS {
+            for i in 0..n {
+                let (sum_x, sum_y) = neighbors[i].iter().fold((0.0, 0.0), |(sx, sy), &(j, w)| (sx + w * x[j], sy + w * y[j]));
+                x[i] = (sum_x + anchor_weight * anchor_x[i]) / (degree[i] + anchor_weight);
+                y[i] = (sum_y + anchor_weight * anchor_y[i]) / (degree[i] + anchor_weight);
+            }
+        }
+    }
+    x.into_iter().zip(y).collect()
+}
+
+/// order and cuts in preorder of the recursive bisection of the positions, as used by set_solution_recursive_bisection:
+/// each part is sorted along the longer side of its region and cut at half of its area
+pub fn slicing_order_from_positions(positions: &[(f64, f64)], modules: &[Rectangle]) -> (Vec<Int>, Vec<usize>) {
+    fn visit(positions: &[(f64, f64)], areas: &[f64], mut part: Vec<Int>, region: (f64, f64), order: &mut Vec<Int>, cuts: &mut Vec<usize>) {
+        if part.len() == 1 {
+            order.push(part[0]);
+            return;
+        }
+        if region.0 >= region.1 {
+            part.sort_by(|&a, &b| positions[a].0.total_cmp(&positions[b].0));
+        }
+        else {
+            part.sort_by(|&a, &b| positions[a].1.total_cmp(&positions[b].1));
+        }
+        let total: f64 = part.iter().map(|&i| areas[i]).sum();
+        let mut prefix = 0.0;
+        let mut mid = 1;
+        for (k, &i) in part[..part.len() - 1].iter().enumerate() {
+            // last position whose prefix area is closest to half of the area
+            if (2.0 * (prefix + areas[i]) - total).abs() <= (2.0 * prefix - total).abs() || k == 0 {
+                mid = k + 1;
+            }
+            prefix += areas[i];
+        }
+        let share = part[..mid].iter().map(|&i| areas[i]).sum::<f64>() / total;
+        let (_, region1, region2) = split_region(region, share);
+        if part.len() > 2 {
+            cuts.push(order.len() + mid);
+        }
+        let second = part.split_off(mid);
+        visit(positions, areas, part, region1, order, cuts);
+        visit(positions, areas, second, region2, order, cuts);
+    }
+    let areas: Vec<f64> = modules.iter().map(|rect| rect.area() as f64).collect();
+    let side = areas.iter().sum::<f64>().sqrt();
+    let mut order: Vec<Int> = Vec::with_capacity(modules.len());
+    let mut cuts: Vec<usize> = Vec::new();
+    visit(positions, &areas, (0..modules.len()).collect(), (side, side), &mut order, &mut cuts);
+    (order, cuts)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_slicing_order() {
+        // 2 x 2 grid
+        let positions = vec![(1.0, 3.0), (3.0, 3.0), (1.0, 1.0), (3.0, 1.0)];
+        let modules = vec![Rectangle::new(2, 2); 4];
+        let (order, cuts) = slicing_order_from_positions(&positions, &modules);
+        assert_eq!(cuts, vec![2]);
+        assert_eq!(order, vec![2, 0, 3, 1]);
+        assert_eq!(spread(&[5.0, 1.0], &[1.0, 3.0], 8.0), vec![7.0, 3.0]);
+    }
+
+    #[test]
+    fn test_force_directed_placement() {
+        // four connected pairs
+        let nets: Vec<Net> = (0..4).map(|i| Net{pins: vec![2 * i, 2 * i + 1], id: i}).collect();
+        let graph = Hypergraph::new(8, &nets);
+        let modules = vec![Rectangle::new(2, 2); 8];
+        let positions = force_directed_placement(&graph, &modules, &mut StdRng::seed_from_u64(0));
+        let side = 32.0_f64.sqrt();
+        assert!(positions.iter().all(|&(x, y)| (0.0..=side).contains(&x) && (0.0..=side).contains(&y)));
+        let distance = |a: usize, b: usize| (positions[a].0 - positions[b].0).abs() + (positions[a].1 - positions[b].1).abs();
+        for i in (0..8).step_by(2) {
+            let unconnected = (0..8).filter(|&j| j / 2 != i / 2).map(|j| distance(i, j)).fold(f64::MAX, f64::min);
+            assert!(distance(i, i + 1) < unconnected);
+        }
+    }
+}
@@ -0,0 +1,383 @@
+use crate::definitions::*;
+use crate::floorplan_common::*;
+use crate::contour::Contour;
+use crate::crossover::*;
+use rand::prelude::*;
+
+const NO_NODE: usize = usize::MAX;
+
+// node of the tree, indexed by slot
+// left child: module placed right of and adjacent to its parent
+// right child: module placed above its parent at the same x-coordinate
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BStarNode {
+    left: usize,
+    right: usize,
+    parent: usize,
+}
+
+impl Default for BStarNode {
+    fn default() -> Self {
+        BStarNode { left: NO_NODE, right: NO_NODE, parent: NO_NODE }
+    }
+}
+
+// tree nodes, module in each slot, root slot, rotated rectangles
+pub type BStarTreeSolution = (Vec<BStarNode>, Vec<Int>, usize, Vec<Rectangle>);
+
+#[derive(Debug)]
+pub enum BTMoveType {
+    RotateModule(usize),
+    SwapModules(usize, usize), // slots
+    MoveModule(usize, usize, bool), // module, new parent module, insert as left child
+}
+
+#[derive(Debug, Default)]
+pub struct BStarTree {
+    modules: Vec<Rectangle>,
+    nets: Vec<Net>,
+    nodes: Vec<BStarNode>,
+    module_at: Vec<Int>, // module placed in slot i
+    root: usize,
+    backup: (Vec<BStarNode>, Vec<Int>, usize), // state before the last MoveModule
+    placement: Floorplan,
+    contour: Contour,
+    stack: Vec<usize>,
+
+    cost_function: CostFunction,
+    current_cost: f64,
+    current_wire: f64,
+    current_area: f64,
+    bounding_box: Rectangle,
+}
+
+impl BStarTree {
+    pub fn new(modules: Vec<Rectangle>, nets: Vec<Net>, alpha: f64) -> Self {
+        let n = modules.len();
+
+        // initial tree: chain of left children, all modules in one row
+        let mut nodes = vec![BStarNode::default(); n];
+        for i in 1..n {
+            nodes[i - 1].left = i;
+            nodes[i].parent = i - 1;
+        }
+        let mut bt = BStarTree {
+            placement: vec![(0, 0, Rectangle::new(0, 0)); n],
+            modules,
+            nets,
+            nodes,
+            module_at: (0..n).collect(),
+            root: 0,
+            cost_function: CostFunction::new(alpha, 1.0, 1.0),
+            ..Default::default()
+        };
+
+        // update cost parameter
+        bt.update();
+        bt.update_cost_function();
+
+        bt
+    }
+
+    pub fn update(&mut self) {
+        self.compute_floorplan();
+        self.current_area = self.bounding_box.area() as f64;
+        self.current_wire = CostFunction::compute_wirelength(&self.placement, &self.nets);
+        self.current_cost = self.cost_function.get_cost(self.current_area, self.current_wire, &self.placement);
+    }
+
+    pub fn update_cost_function(&mut self) {
+        // compute averages for cost function
+        let repetitions = 3 * self.modules.len();
+        let samples = sample_perturbations(self, repetitions, self.cost_function.has_terms());
+        self.cost_function.update_averages(&samples);
+        // perturbations are reversed without update
+        self.update();
+    }
+
+    /// registers additional weighted cost terms and re-estimates their averages
+    pub fn set_cost_terms(&mut self, terms: Vec<WeightedCostTerm>) {
+        self.cost_function.set_terms(terms);
+        self.update_cost_function();
+    }
+
+    /// packs the modules in DFS order, y-coordinates are given by the contour
+    pub fn compute_floorplan(&mut self) {
+        self.contour.clear();
+        self.stack.push(self.root);
+        while let Some(v) = self.stack.pop() {
+            let id = self.module_at[v];
+            let rect = self.modules[id];
+            let parent = self.nodes[v].parent;
+            let x = if parent == NO_NODE {
+                0
+            }
+            else {
+                let (parent_x, _, parent_rect) = self.placement[self.module_at[parent]];
+                if self.nodes[parent].left == v { parent_x + parent_rect.width } else { parent_x }
+            };
+            let y = self.contour.place(x, rect.width, rect.height);
+            self.placement[id] = (x, y, rect);
+
+            // left subtree is packed first
+            if self.nodes[v].right != NO_NODE {
+                self.stack.push(self.nodes[v].right);
+            }
+            if self.nodes[v].left != NO_NODE {
+                self.stack.push(self.nodes[v].left);
+            }
+        }
+
+        // compute bounding box
+        self.bounding_box.width = self.placement.iter().map(|(x, _, r)| x + r.width).max().unwrap();
+        self.bounding_box.height = self.placement.iter().map(|(_, y, r)| y + r.height).max().unwrap();
+    }
+
+    // moves the module in slot v down to a leaf, each module on the path moves one level up
+    // returns the leaf slot
+    fn push_down(&mut self, mut v: usize) -> usize {
+        loop {
+            let child = if self.nodes[v].left != NO_NODE { self.nodes[v].left } else { self.nodes[v].right };
+            if child == NO_NODE {
+                return v;
+            }
+            self.module_at.swap(v, child);
+            v = child;
+        }
+    }
+
+    fn detach_leaf(&mut self, v: usize) {
+        let parent = self.nodes[v].parent;
+        if self.nodes[parent].left == v {
+            self.nodes[parent].left = NO_NODE;
+        }
+        else {
+            self.nodes[parent].right = NO_NODE;
+        }
+        self.nodes[v].parent = NO_NODE;
+    }
+
+    // the previous child of the parent becomes the child of v on the same side
+    fn insert_leaf(&mut self, v: usize, parent: usize, as_left: bool) {
+        let child = if as_left { self.nodes[parent].left } else { self.nodes[parent].right };
+        if as_left {
+            self.nodes[parent].left = v;
+            self.nodes[v].left = child;
+        }
+        else {
+            self.nodes[parent].right = v;
+            self.nodes[v].right = child;
+        }
+        self.nodes[v].parent = parent;
+        if child != NO_NODE {
+            self.nodes[child].parent = v;
+        }
+    }
+
+    fn slot_of(&self, module: Int) -> usize {
+        self.module_at.iter().position(|&m| m == module).unwrap()
+    }
+
+    fn move_module(&mut self, module: Int, parent_module: Int, as_left: bool) {
+        self.backup = (self.nodes.clone(), self.module_at.clone(), self.root);
+        let leaf = self.push_down(self.slot_of(module));
+        self.detach_leaf(leaf);
+        let parent = self.slot_of(parent_module);
+        self.insert_leaf(leaf, parent, as_left);
+    }
+
+    // slots in DFS order
+    fn preorder(nodes: &[BStarNode], root: usize) -> Vec<usize> {
+        let mut order: Vec<usize> = Vec::with_capacity(nodes.len());
+        let mut stack: Vec<usize> = vec![root];
+        while let Some(v) = stack.pop() {
+            order.push(v);
+            if nodes[v].right != NO_NODE {
+                stack.push(nodes[v].right);
+            }
+            if nodes[v].left != NO_NODE {
+                stack.push(nodes[v].left);
+            }
+        }
+        order
+    }
+}
+
+impl Mutation<BTMoveType> for BStarTree {
+    fn get_random_move(&mut self) -> BTMoveType {
+        let mut rng: ThreadRng = rand::thread_rng();
+        let n = self.modules.len();
+        let r = if n < 2 { 0 } else { rng.gen_range(0..3) };
+        let two_random = |rng: &mut ThreadRng| -> (usize, usize) {
+            let a = rng.gen_range(0..n);
+            let b = (a + rng.gen_range(1..n)) % n;
+            (a, b)
+        };
+        let move_type: BTMoveType = match r {
+            0 => BTMoveType::RotateModule(rng.gen_range(0..n)),
+            1 => {
+                let (a, b) = two_random(&mut rng);
+                BTMoveType::SwapModules(a, b)
+            }
+            _ => {
+                let (a, b) = two_random(&mut rng);
+                BTMoveType::MoveModule(a, b, rng.gen_bool(0.5))
+            }
+        };
+        move_type
+    }
+
+    // update = false reverses the previously applied move
+    fn apply_move(&mut self, _move: &BTMoveType, update: bool) {
+        match *_move {
+            BTMoveType::RotateModule(a) => self.modules[a] = self.modules[a].transpose(),
+            BTMoveType::SwapModules(a, b) => self.module_at.swap(a, b),
+            BTMoveType::MoveModule(a, b, as_left) => {
+                if update {
+                    self.move_module(a, b, as_left);
+                }
+                else {
+                    (self.nodes, self.module_at, self.root) = self.backup.clone();
+                }
+            }
+        }
+        if update {
+            self.update()
+        }
+    }
+}
+
+impl FloorCost for BStarTree {
+    fn get_floor_area(&self) -> f64 {
+        self.current_area
+    }
+
+    fn get_floor_wire(&self) -> f64 {
+        self.current_wire
+    }
+}
+
+impl FloorPlan for BStarTree {
+    fn get_floorplan(&self) -> Floorplan {
+        self.placement.clone()
+    }
+}
+
+impl Cost for BStarTree {
+    fn get_cost(&self) -> f64 {
+        self.current_cost
+    }
+}
+
+impl Solution<BStarTreeSolution> for BStarTree {
+    fn copy_solution(&self) -> BStarTreeSolution {
+        (self.nodes.clone(), self.module_at.clone(), self.root, self.modules.clone())
+    }
+
+    fn set_solution(&mut self, solution: BStarTreeSolution) {
+        (self.nodes, self.module_at, self.root, self.modules) = solution;
+        self.update()
+    }
+}
+
+impl RandomSolution<BStarTreeSolution> for BStarTree {
+    fn random_solution(&self) -> BStarTreeSolution {
+        let mut rng: ThreadRng = rand::thread_rng();
+        let n = self.modules.len();
+        let mut nodes = vec![BStarNode::default(); n];
+        // attach each slot to a random free child position of the tree built so far
+        let mut free: Vec<(usize, bool)> = vec![(0, true), (0, false)];
+        for v in 1..n {
+            let (parent, as_left) = free.swap_remove(rng.gen_range(0..free.len()));
+            if as_left {
+                nodes[parent].left = v;
+            }
+            else {
+                nodes[parent].right = v;
+            }
+            nodes[v].parent = parent;
+            free.push((v, true));
+            free.push((v, false));
+        }
+        let mut module_at: Vec<Int> = (0..n).collect();
+        module_at.shuffle(&mut rng);
+        let mut rect = self.modules.clone();
+        for r in rect.iter_mut() {
+            if rng.gen_bool(0.5) {
+                *r = r.transpose();
+            }
+        }
+        (nodes, module_at, 0, rect)
+    }
+}
+
+impl Crossover<BStarTreeSolution> for BStarTree {
+    // keeps the tree of a and fills its slots in DFS order with the order crossover of the DFS module sequences
+    fn crossover(&self, a: &BStarTreeSolution, b: &BStarTreeSolution) -> BStarTreeSolution {
+        let slots_a = BStarTree::preorder(&a.0, a.2);
+        let slots_b = BStarTree::preorder(&b.0, b.2);
+        let sequence_a: Vec<Int> = slots_a.iter().map(|&v| a.1[v]).collect();
+        let sequence_b: Vec<Int> = slots_b.iter().map(|&v| b.1[v]).collect();
+
+        let (sequence, from_a) = order_crossover(&sequence_a, &sequence_b, random_cut(sequence_a.len()));
+        let mut module_at = a.1.clone();
+        for (&v, &id) in slots_a.iter().zip(sequence.iter()) {
+            module_at[v] = id;
+        }
+        let rect: Vec<Rectangle> = (0..from_a.len())
+            .map(|i| if from_a[i] { a.3[i] } else { b.3[i] })
+            .collect();
+        (a.0.clone(), module_at, a.2, rect)
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compaction::find_overlaps;
+
+    fn random_modules(n: usize) -> Vec<Rectangle> {
+        let mut rng = StdRng::seed_from_u64(0);
+        (0..n).map(|_| Rectangle::new(rng.gen_range(1..10), rng.gen_range(1..10))).collect()
+    }
+
+    #[test]
+    fn test_pack() {
+        // module 1 right of module 0, module 2 on top of module 0
+        let modules = vec![Rectangle::new(4, 2), Rectangle::new(2, 5), Rectangle::new(3, 1)];
+        let mut bt = BStarTree::new(modules.clone(), Vec::new(), 1.0);
+        let mut nodes = vec![BStarNode::default(); 3];
+        (nodes[0].left, nodes[0].right) = (1, 2);
+        (nodes[1].parent, nodes[2].parent) = (0, 0);
+        bt.set_solution((nodes, vec![0, 1, 2], 0, modules));
+        assert_eq!(bt.get_floorplan(), vec![
+            (0, 0, Rectangle::new(4, 2)),
+            (4, 0, Rectangle::new(2, 5)),
+            (0, 2, Rectangle::new(3, 1)),
+        ]);
+        assert_eq!(bt.get_floor_area(), 30.0);
+    }
+
+    #[test]
+    fn test_moves() {
+        let mut bt = BStarTree::new(random_modules(15), Vec::new(), 1.0);
+        let moves = [BTMoveType::RotateModule(3), BTMoveType::SwapModules(0, 7), BTMoveType::MoveModule(2, 9, false)];
+        for _move in moves.iter().chain((0..200).map(|_| bt.get_random_move()).collect::<Vec<_>>().iter()) {
+            let solution = bt.copy_solution();
+            bt.apply_move(_move, true);
+            assert!(find_overlaps(&bt.get_floorplan()).is_empty());
+            bt.apply_move(_move, false);
+            assert_eq!(bt.copy_solution(), solution);
+            // keep the move to walk through different trees
+            bt.apply_move(_move, true);
+        }
+    }
+
+    #[test]
+    fn test_random_solution() {
+        let mut bt = BStarTree::new(random_modules(15), Vec::new(), 1.0);
+        for _ in 0..20 {
+            bt.set_solution(bt.random_solution());
+            assert!(find_overlaps(&bt.get_floorplan()).is_empty());
+        }
+    }
+}
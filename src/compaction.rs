@@ -0,0 +1,169 @@
+use crate::definitions::*;
+
+/// area of the bounding box before and after compaction
+#[derive(Debug, Clone, Copy)]
+pub struct CompactionReport {
+    pub area_before: Int,
+    pub area_after: Int,
+    pub rounds: usize,
+}
+
+// stop alternating compaction after this many rounds
+const MAX_ROUNDS: usize = 100;
+
+pub fn get_bounding_box(plan: &Floorplan) -> Rectangle {
+    plan.iter().fold(Rectangle::new(0, 0), |acc, (x, y, rect)| {
+        Rectangle::new(acc.width.max(x + rect.width), acc.height.max(y + rect.height))
+    })
+}
+
+/// pairs of modules whose rectangles intersect in more than a boundary
+pub fn find_overlaps(plan: &Floorplan) -> Vec<(usize, usize)> {
+    let mut order: Vec<usize> = (0..plan.len()).collect();
+    order.sort_by_key(|&i| plan[i].0);
+    let mut overlaps: Vec<(usize, usize)> = Vec::new();
+    // sweep line over x, active modules intersect the sweep line
+    let mut active: Vec<usize> = Vec::new();
+    for &i in order.iter() {
+        let (x, y, rect) = plan[i];
+        active.retain(|&j| plan[j].0 + plan[j].2.width > x);
+        for &j in active.iter() {
+            let (_, y2, rect2) = plan[j];
+            if y < y2 + rect2.height && y2 < y + rect.height {
+                overlaps.push((i.min(j), i.max(j)));
+            }
+        }
+        active.push(i);
+    }
+    overlaps
+}
+
+fn transpose(plan: &mut Floorplan) {
+    for (x, y, rect) in plan.iter_mut() {
+        (*x, *y, *rect) = (*y, *x, rect.transpose());
+    }
+}
+
+// modules j that have to stay left of i: overlapping y-range and left of i
+// predecessors are returned for modules sorted by x
+fn horizontal_constraints(plan: &Floorplan) -> (Vec<usize>, Vec<Vec<usize>>) {
+    let mut order: Vec<usize> = (0..plan.len()).collect();
+    order.sort_by_key(|&i| plan[i].0);
+    let mut predecessors: Vec<Vec<usize>> = vec![Vec::new(); plan.len()];
+    for (k, &i) in order.iter().enumerate() {
+        let (x, y, rect) = plan[i];
+        for &j in order[..k].iter() {
+            let (x2, y2, rect2) = plan[j];
+            if x2 + rect2.width <= x && y < y2 + rect2.height && y2 < y + rect.height {
+                predecessors[i].push(j);
+            }
+        }
+    }
+    (order, predecessors)
+}
+
+/// moves every module as far to the left as possible, returns true if a module moved
+pub fn compact_left(plan: &mut Floorplan) -> bool {
+    let (order, predecessors) = horizontal_constraints(plan);
+    let mut moved = false;
+    for &i in order.iter() {
+        let x = predecessors[i].iter()
+            .map(|&j| plan[j].0 + plan[j].2.width)
+            .max()
+            .unwrap_or(0);
+        moved |= x != plan[i].0;
+        plan[i].0 = x;
+    }
+    moved
+}
+
+/// moves every module as far down as possible, returns true if a module moved
+pub fn compact_down(plan: &mut Floorplan) -> bool {
+    transpose(plan);
+    let moved = compact_left(plan);
+    transpose(plan);
+    moved
+}
+
+/// alternating left and down compaction of a legal floorplan until no module moves
+/// returns the overlapping modules if the floorplan is not legal
+pub fn compact(plan: &mut Floorplan) -> Result<CompactionReport, Vec<(usize, usize)>> {
+    let overlaps = find_overlaps(plan);
+    if !overlaps.is_empty() {
+        return Err(overlaps);
+    }
+    let area_before = get_bounding_box(plan).area();
+    let mut rounds = 0;
+    while rounds < MAX_ROUNDS {
+        rounds += 1;
+        let moved_left = compact_left(plan);
+        let moved_down = compact_down(plan);
+        if !moved_left && !moved_down {
+            break;
+        }
+    }
+    let area_after = get_bounding_box(plan).area();
+    debug_assert!(find_overlaps(plan).is_empty());
+    Ok(CompactionReport { area_before, area_after, rounds })
+}
+
+// places each module in the middle of its leftmost and rightmost position inside the bounding box
+fn redistribute_horizontal(plan: &mut Floorplan) {
+    let width = get_bounding_box(plan).width;
+    let (order, predecessors) = horizontal_constraints(plan);
+    let mut min_x: Vec<Int> = vec![0; plan.len()];
+    for &i in order.iter() {
+        min_x[i] = predecessors[i].iter().map(|&j| min_x[j] + plan[j].2.width).max().unwrap_or(0);
+    }
+    let mut max_x: Vec<Int> = plan.iter().map(|(_, _, rect)| width - rect.width).collect();
+    for &i in order.iter().rev() {
+        for &j in predecessors[i].iter() {
+            max_x[j] = max_x[j].min(max_x[i] - plan[j].2.width);
+        }
+    }
+    for i in 0..plan.len() {
+        plan[i].0 = (min_x[i] + max_x[i]) / 2;
+    }
+}
+
+/// spreads the dead space evenly between the modules without changing the bounding box
+pub fn redistribute_whitespace(plan: &mut Floorplan) {
+    redistribute_horizontal(plan);
+    transpose(plan);
+    redistribute_horizontal(plan);
+    transpose(plan);
+    debug_assert!(find_overlaps(plan).is_empty());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_overlaps() {
+        let plan: Floorplan = vec![
+            (0, 0, Rectangle::new(2, 2)),
+            (2, 0, Rectangle::new(2, 2)),
+            (1, 1, Rectangle::new(2, 2)),
+            (0, 2, Rectangle::new(1, 1)),
+        ];
+        assert_eq!(find_overlaps(&plan), vec![(0, 2), (1, 2)]);
+    }
+
+    #[test]
+    fn test_compact() {
+        let mut plan: Floorplan = vec![
+            (0, 0, Rectangle::new(2, 2)),
+            (5, 1, Rectangle::new(2, 2)),
+            (1, 6, Rectangle::new(3, 1)),
+        ];
+        let report = compact(&mut plan).unwrap();
+        assert_eq!(plan, vec![
+            (0, 0, Rectangle::new(2, 2)),
+            (2, 0, Rectangle::new(2, 2)),
+            (0, 2, Rectangle::new(3, 1)),
+        ]);
+        assert_eq!(report.area_before, 49);
+        assert_eq!(report.area_after, 12);
+    }
+}
@@ -0,0 +1,280 @@
+use crate::definitions::*;
+use crate::cost_terms::get_gap;
+use crate::compaction::get_bounding_box;
+
+/// edge of the chip in boundary constraints
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Edge {
+    Left(),
+    Right(),
+    Top(),
+    Bottom(),
+    Any(),
+}
+
+impl From<String> for Edge {
+    fn from(s: String) -> Self {
+        match s.as_str() {
+            "left" => Edge::Left(),
+            "right" => Edge::Right(),
+            "top" => Edge::Top(),
+            "bottom" => Edge::Bottom(),
+            "any" => Edge::Any(),
+            _ => panic!("unknown edge {}", s),
+        }
+    }
+}
+
+/// hard constraint between two modules
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PlacementConstraint {
+    AlignBottom(usize, usize),
+    AlignTop(usize, usize),
+    AlignLeft(usize, usize),
+    AlignRight(usize, usize),
+    Abut(usize, usize), // modules share a boundary segment
+    LeftOf(usize, usize),
+    Below(usize, usize),
+    Fixed(usize, Int, Int), // module is pre-placed at (x, y) and must not overlap other modules
+    Boundary(usize, Edge), // module touches the edge of the bounding box
+    Region(usize, Int, Int, Rectangle), // module lies inside the rectangle placed at (x, y)
+}
+
+// depth of the overlap of two placed modules, 0 if they do not overlap
+fn get_overlap(a: (Int, Int, Rectangle), b: (Int, Int, Rectangle)) -> Int {
+    let (x1, y1, rect1) = a;
+    let (x2, y2, rect2) = b;
+    let overlap_x = (x1 + rect1.width).min(x2 + rect2.width).saturating_sub(x1.max(x2));
+    let overlap_y = (y1 + rect1.height).min(y2 + rect2.height).saturating_sub(y1.max(y2));
+    overlap_x.min(overlap_y)
+}
+
+// area of the intersection of two placed rectangles
+fn get_overlap_area(a: (Int, Int, Rectangle), b: (Int, Int, Rectangle)) -> Int {
+    let (x1, y1, rect1) = a;
+    let (x2, y2, rect2) = b;
+    let overlap_x = (x1 + rect1.width).min(x2 + rect2.width).saturating_sub(x1.max(x2));
+    let overlap_y = (y1 + rect1.height).min(y2 + rect2.height).saturating_sub(y1.max(y2));
+    overlap_x * overlap_y
+}
+
+impl PlacementConstraint {
+    /// distance from satisfying the constraint, 0 if it is satisfied
+    pub fn penalty(&self, plan: &Floorplan) -> Int {
+        let top = |i: usize| plan[i].1 + plan[i].2.height;
+        let right = |i: usize| plan[i].0 + plan[i].2.width;
+        match *self {
+            PlacementConstraint::AlignBottom(a, b) => plan[a].1.abs_diff(plan[b].1),
+            PlacementConstraint::AlignTop(a, b) => top(a).abs_diff(top(b)),
+            PlacementConstraint::AlignLeft(a, b) => plan[a].0.abs_diff(plan[b].0),
+            PlacementConstraint::AlignRight(a, b) => right(a).abs_diff(right(b)),
+            PlacementConstraint::Abut(a, b) => {
+                let gap = get_gap(plan[a], plan[b]);
+                // touching only at a corner
+                let overlap_x = right(a).min(right(b)) > plan[a].0.max(plan[b].0);
+                let overlap_y = top(a).min(top(b)) > plan[a].1.max(plan[b].1);
+                if gap == 0 && !overlap_x && !overlap_y { 1 } else { gap }
+            }
+            PlacementConstraint::LeftOf(a, b) => right(a).saturating_sub(plan[b].0),
+            PlacementConstraint::Below(a, b) => top(a).saturating_sub(plan[b].1),
+            PlacementConstraint::Fixed(a, x, y) => {
+                let displacement = plan[a].0.abs_diff(x) + plan[a].1.abs_diff(y);
+                let overlap: Int = (0..plan.len()).filter(|&j| j != a).map(|j| get_overlap(plan[a], plan[j])).sum();
+                displacement + overlap
+            }
+            PlacementConstraint::Boundary(a, edge) => {
+                let bounding_box = get_bounding_box(plan);
+                let left = plan[a].0;
+                let bottom = plan[a].1;
+                let right = bounding_box.width - (plan[a].0 + plan[a].2.width);
+                let top = bounding_box.height - (plan[a].1 + plan[a].2.height);
+                match edge {
+                    Edge::Left() => left,
+                    Edge::Right() => right,
+                    Edge::Top() => top,
+                    Edge::Bottom() => bottom,
+                    Edge::Any() => left.min(right).min(top).min(bottom),
+                }
+            }
+            // area of the module outside of the region
+            PlacementConstraint::Region(a, x, y, region) => plan[a].2.area() - get_overlap_area(plan[a], (x, y, region)),
+        }
+    }
+}
+
+/// modules placed symmetrically to a common vertical axis
+#[derive(Debug, Clone, Default)]
+pub struct SymmetryGroup {
+    pub pairs: Vec<(usize, usize)>,   // modules mirrored at the axis
+    pub self_symmetric: Vec<usize>, // modules centered on the axis
+}
+
+impl SymmetryGroup {
+    /// symmetric counterpart of each member
+    pub fn counterparts(&self) -> Vec<(usize, usize)> {
+        self.pairs.iter()
+            .flat_map(|&(a, b)| [(a, b), (b, a)])
+            .chain(self.self_symmetric.iter().map(|&s| (s, s)))
+            .collect()
+    }
+
+    /// deviation from a symmetric placement, 0 if the group is symmetric
+    /// the sum of the doubled centers of a pair, i.e. twice the doubled axis, is compared with the one of the first pair
+    /// or twice the doubled center of the first self-symmetric module, so no coordinate is divided
+    pub fn get_asymmetry(&self, plan: &Floorplan) -> Int {
+        let doubled_center = |i: usize| 2 * plan[i].0 + plan[i].2.width;
+        let axis = match (self.pairs.first(), self.self_symmetric.first()) {
+            (Some(&(a, b)), _) => doubled_center(a) + doubled_center(b),
+            (None, Some(&s)) => 2 * doubled_center(s),
+            (None, None) => return 0,
+        };
+        let pairs: Int = self.pairs.iter()
+            .map(|&(a, b)| (doubled_center(a) + doubled_center(b)).abs_diff(axis) + plan[a].1.abs_diff(plan[b].1))
+            .sum();
+        let self_symmetric: Int = self.self_symmetric.iter().map(|&s| (2 * doubled_center(s)).abs_diff(axis)).sum();
+        pairs + self_symmetric
+    }
+}
+
+/// placement constraints given after the net list of a .floor file,
+/// one constraint per line: a keyword followed by module ids
+/// near id1 id2
+/// apart id1 id2
+/// align_bottom, align_top, align_left, align_right id1 id2
+/// abut id1 id2
+/// left_of id1 id2
+/// below id1 id2
+/// fixed id x y
+/// boundary id left|right|top|bottom|any
+/// region id x y width height
+/// symmetric_pair group id1 id2
+/// self_symmetric group id
+#[derive(Debug, Clone, Default)]
+pub struct Constraints {
+    pub near: Vec<(usize, usize)>,
+    pub apart: Vec<(usize, usize)>,
+    pub placement: Vec<PlacementConstraint>,
+    pub symmetry_groups: Vec<SymmetryGroup>,
+}
+
+impl Constraints {
+    pub fn is_empty(&self) -> bool {
+        self.near.is_empty() && self.apart.is_empty() && self.placement.is_empty() && self.symmetry_groups.is_empty()
+    }
+
+    fn get_symmetry_group(&mut self, group: usize) -> &mut SymmetryGroup {
+        if group >= self.symmetry_groups.len() {
+            self.symmetry_groups.resize(group + 1, SymmetryGroup::default());
+        }
+        &mut self.symmetry_groups[group]
+    }
+
+    /// pre-placed modules and their coordinates
+    pub fn get_fixed(&self) -> Vec<(usize, Int, Int)> {
+        self.placement.iter()
+            .filter_map(|c| match *c { PlacementConstraint::Fixed(a, x, y) => Some((a, x, y)), _ => None })
+            .collect()
+    }
+
+    /// regions and their modules
+    pub fn get_regions(&self) -> Vec<(usize, Int, Int, Rectangle)> {
+        self.placement.iter()
+            .filter_map(|c| match *c { PlacementConstraint::Region(a, x, y, region) => Some((a, x, y, region)), _ => None })
+            .collect()
+    }
+
+    /// placement constraints that are not satisfied by the floorplan
+    pub fn find_violations(&self, plan: &Floorplan) -> Vec<PlacementConstraint> {
+        self.placement.iter().filter(|c| c.penalty(plan) > 0).copied().collect()
+    }
+
+    pub fn parse_line(&mut self, line: &str, num_blocks: usize) {
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+        if tokens.is_empty() {
+            return;
+        }
+        if let ["boundary", id, edge] = tokens.as_slice() {
+            let id = id.parse::<usize>().unwrap();
+            if id >= num_blocks {
+                panic!("constraint '{}' refers to unknown module {}", line, id);
+            }
+            self.placement.push(PlacementConstraint::Boundary(id, Edge::from(edge.to_string())));
+            return;
+        }
+        let ids: Vec<usize> = tokens[1..].iter().map(|x| x.parse::<usize>().unwrap()).collect();
+        // fixed modules and regions are followed by coordinates, symmetry constraints start with the group
+        let modules = match tokens[0] {
+            "fixed" | "region" => &ids[..1.min(ids.len())],
+            "symmetric_pair" | "self_symmetric" => &ids[1.min(ids.len())..],
+            _ => &ids[..],
+        };
+        if let Some(&id) = modules.iter().find(|&&id| id >= num_blocks) {
+            panic!("constraint '{}' refers to unknown module {}", line, id);
+        }
+        match (tokens[0], ids.as_slice()) {
+            ("near", &[a, b]) => self.near.push((a, b)),
+            ("apart", &[a, b]) => self.apart.push((a, b)),
+            ("align_bottom", &[a, b]) => self.placement.push(PlacementConstraint::AlignBottom(a, b)),
+            ("align_top", &[a, b]) => self.placement.push(PlacementConstraint::AlignTop(a, b)),
+            ("align_left", &[a, b]) => self.placement.push(PlacementConstraint::AlignLeft(a, b)),
+            ("align_right", &[a, b]) => self.placement.push(PlacementConstraint::AlignRight(a, b)),
+            ("abut", &[a, b]) => self.placement.push(PlacementConstraint::Abut(a, b)),
+            ("left_of", &[a, b]) => self.placement.push(PlacementConstraint::LeftOf(a, b)),
+            ("below", &[a, b]) => self.placement.push(PlacementConstraint::Below(a, b)),
+            ("fixed", &[a, x, y]) => self.placement.push(PlacementConstraint::Fixed(a, x, y)),
+            ("region", &[a, x, y, w, h]) => self.placement.push(PlacementConstraint::Region(a, x, y, Rectangle::new(w, h))),
+            ("symmetric_pair", &[g, a, b]) => self.get_symmetry_group(g).pairs.push((a, b)),
+            ("self_symmetric", &[g, a]) => self.get_symmetry_group(g).self_symmetric.push(a),
+            _ => panic!("invalid constraint '{}'", line),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_penalty() {
+        let plan: Floorplan = vec![
+            (0, 0, Rectangle::new(2, 2)),
+            (2, 1, Rectangle::new(2, 2)),
+            (4, 3, Rectangle::new(1, 1)),
+        ];
+        assert_eq!(PlacementConstraint::AlignBottom(0, 1).penalty(&plan), 1);
+        assert_eq!(PlacementConstraint::AlignTop(1, 2).penalty(&plan), 1);
+        assert_eq!(PlacementConstraint::Abut(0, 1).penalty(&plan), 0);
+        assert_eq!(PlacementConstraint::Abut(1, 2).penalty(&plan), 1);
+        assert_eq!(PlacementConstraint::Abut(0, 2).penalty(&plan), 3);
+        assert_eq!(PlacementConstraint::LeftOf(0, 1).penalty(&plan), 0);
+        assert_eq!(PlacementConstraint::LeftOf(1, 0).penalty(&plan), 4);
+        assert_eq!(PlacementConstraint::Below(1, 2).penalty(&plan), 0);
+        assert_eq!(PlacementConstraint::Fixed(2, 4, 3).penalty(&plan), 0);
+        assert_eq!(PlacementConstraint::Fixed(1, 1, 1).penalty(&plan), 1);
+        assert_eq!(PlacementConstraint::Boundary(1, Edge::Right()).penalty(&plan), 1);
+        assert_eq!(PlacementConstraint::Boundary(1, Edge::Any()).penalty(&plan), 1);
+        assert_eq!(PlacementConstraint::Boundary(2, Edge::Any()).penalty(&plan), 0);
+        assert_eq!(PlacementConstraint::Region(0, 0, 0, Rectangle::new(10, 10)).penalty(&plan), 0);
+        assert_eq!(PlacementConstraint::Region(0, 1, 0, Rectangle::new(10, 10)).penalty(&plan), 2);
+
+        // axis at x = 3
+        let group = SymmetryGroup { pairs: vec![(0, 1)], self_symmetric: vec![2] };
+        let mut plan: Floorplan = vec![
+            (0, 0, Rectangle::new(2, 2)),
+            (4, 0, Rectangle::new(2, 2)),
+            (2, 2, Rectangle::new(2, 1)),
+        ];
+        assert_eq!(group.get_asymmetry(&plan), 0);
+        plan[2].0 = 3;
+        assert_eq!(group.get_asymmetry(&plan), 4);
+        // the doubled axis 3.5 of both pairs is not integral
+        let group = SymmetryGroup { pairs: vec![(0, 1), (2, 3)], self_symmetric: Vec::new() };
+        let plan: Floorplan = vec![
+            (0, 0, Rectangle::new(1, 1)),
+            (2, 0, Rectangle::new(2, 1)),
+            (0, 1, Rectangle::new(1, 1)),
+            (2, 1, Rectangle::new(2, 1)),
+        ];
+        assert_eq!(group.get_asymmetry(&plan), 0);
+    }
+}
@@ -0,0 +1,66 @@
+use crate::definitions::*;
+
+/// horizontal contour of packed modules, used for packing in compacted tree representations
+/// stored as consecutive segments (x_start, x_end, height) covering [0, Int::MAX)
+#[derive(Debug, Clone)]
+pub struct Contour {
+    segments: Vec<(Int, Int, Int)>,
+    buffer: Vec<(Int, Int, Int)>,
+}
+
+impl Default for Contour {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Contour {
+    pub fn new() -> Self {
+        Contour { segments: vec![(0, Int::MAX, 0)], buffer: Vec::new() }
+    }
+
+    pub fn clear(&mut self) {
+        self.segments.clear();
+        self.segments.push((0, Int::MAX, 0));
+    }
+
+    /// drops a module of given width at position x onto the contour
+    /// returns the y-coordinate of the module and raises the contour to its top
+    pub fn place(&mut self, x: Int, width: Int, height: Int) -> Int {
+        let x_end = x + width;
+        let first = self.segments.partition_point(|s| s.1 <= x);
+        let mut last = first;
+        let mut y = 0;
+        while last < self.segments.len() && self.segments[last].0 < x_end {
+            y = y.max(self.segments[last].2);
+            last += 1;
+        }
+        // split partially covered segments at the borders
+        self.buffer.clear();
+        let (left_start, _, left_height) = self.segments[first];
+        if left_start < x {
+            self.buffer.push((left_start, x, left_height));
+        }
+        self.buffer.push((x, x_end, y + height));
+        let (_, right_end, right_height) = self.segments[last - 1];
+        if right_end > x_end {
+            self.buffer.push((x_end, right_end, right_height));
+        }
+        self.segments.splice(first..last, self.buffer.drain(..));
+        y
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_place() {
+        let mut contour = Contour::new();
+        assert_eq!(contour.place(0, 4, 2), 0);
+        assert_eq!(contour.place(4, 2, 5), 0);
+        assert_eq!(contour.place(2, 3, 1), 5);
+        assert_eq!(contour.place(0, 2, 1), 2);
+        assert_eq!(contour.segments, vec![(0, 2, 3), (2, 5, 6), (5, 6, 5), (6, Int::MAX, 0)]);
+    }
+}
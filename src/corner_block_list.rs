@@ -0,0 +1,284 @@
+use crate::definitions::*;
+use crate::floorplan_common::*;
+use crate::crossover::*;
+use rand::prelude::*;
+
+// largest number of T-junctions drawn for a random move
+const MAX_JUNCTIONS: usize = 4;
+
+// block sequence S, orientations L (true = horizontal insertion), T-junctions T, rotated rectangles
+pub type CornerBlockListSolution = (Vec<Int>, Vec<bool>, Vec<usize>, Vec<Rectangle>);
+
+#[derive(Debug)]
+pub enum CBLMoveType {
+    RotateModule(usize),
+    SwapModules(usize, usize), // positions in S
+    FlipOrientation(usize),
+    ChangeJunctions(usize, usize, usize), // position, toggles T between both values
+}
+
+#[derive(Debug, Default)]
+pub struct CornerBlockList {
+    modules: Vec<Rectangle>,
+    nets: Vec<Net>,
+    sequence: Vec<Int>,
+    orientation: Vec<bool>,
+    junctions: Vec<usize>,
+    right_blocks: Vec<Int>, // blocks on the right boundary, top most last
+    top_blocks: Vec<Int>,   // blocks on the top boundary, right most last
+    left_of: Vec<Vec<Int>>, // blocks left of each vertical segment
+    below: Vec<Vec<Int>>,   // blocks below each horizontal segment
+    left_segment: Vec<usize>,   // vertical segment on the left side of each block
+    bottom_segment: Vec<usize>, // horizontal segment on the bottom side of each block
+    placement: Floorplan,
+
+    cost_function: CostFunction,
+    current_cost: f64,
+    current_wire: f64,
+    current_area: f64,
+    bounding_box: Rectangle,
+}
+
+impl CornerBlockList {
+    pub fn new(modules: Vec<Rectangle>, nets: Vec<Net>, alpha: f64) -> Self {
+        let n = modules.len();
+
+        // initial list: every block is inserted right of the previous one
+        let mut cbl = CornerBlockList {
+            placement: vec![(0, 0, Rectangle::new(0, 0)); n],
+            modules,
+            nets,
+            sequence: (0..n).collect(),
+            orientation: vec![false; n],
+            junctions: vec![0; n],
+            left_of: vec![Vec::new()],
+            below: vec![Vec::new()],
+            left_segment: vec![0; n],
+            bottom_segment: vec![0; n],
+            cost_function: CostFunction::new(alpha, 1.0, 1.0),
+            ..Default::default()
+        };
+
+        // update cost parameter
+        cbl.update();
+        cbl.update_cost_function();
+
+        cbl
+    }
+
+    pub fn update(&mut self) {
+        self.compute_floorplan();
+        self.current_area = self.bounding_box.area() as f64;
+        self.current_wire = CostFunction::compute_wirelength(&self.placement, &self.nets);
+        self.current_cost = self.cost_function.get_cost(self.current_area, self.current_wire, &self.placement);
+    }
+
+    pub fn update_cost_function(&mut self) {
+        // compute averages for cost function
+        let repetitions = 3 * self.modules.len();
+        let samples = sample_perturbations(self, repetitions, self.cost_function.has_terms());
+        self.cost_function.update_averages(&samples);
+        // perturbations are reversed without update
+        self.update();
+    }
+
+    /// registers additional weighted cost terms and re-estimates their averages
+    pub fn set_cost_terms(&mut self, terms: Vec<WeightedCostTerm>) {
+        self.cost_function.set_terms(terms);
+        self.update_cost_function();
+    }
+
+    /// decodes the list by inserting the blocks one after another at the top right corner
+    /// a vertical insertion covers the T + 1 top most blocks of the right boundary,
+    /// a horizontal insertion the T + 1 right most blocks of the top boundary
+    /// coordinates are the longest paths in the constraint graphs of the segments, which are built in insertion order
+    pub fn compute_floorplan(&mut self) {
+        let n = self.sequence.len();
+        self.right_blocks.clear();
+        self.top_blocks.clear();
+        // segment 0 is the left or bottom chip boundary
+        self.left_of.truncate(1);
+        self.below.truncate(1);
+        self.left_of[0].clear();
+        self.below[0].clear();
+        for i in 0..n {
+            let id = self.sequence[i];
+            if i == 0 {
+                self.left_segment[id] = 0;
+                self.bottom_segment[id] = 0;
+            }
+            else if self.orientation[i] {
+                // horizontal insertion: new segment on top of the covered blocks
+                let k = self.junctions[i].min(self.top_blocks.len() - 1) + 1;
+                let covered: Vec<Int> = self.top_blocks.drain(self.top_blocks.len() - k..).collect();
+                self.left_segment[id] = self.left_segment[covered[0]];
+                self.bottom_segment[id] = self.below.len();
+                self.below.push(covered);
+            }
+            else {
+                // vertical insertion: new segment right of the covered blocks
+                let k = self.junctions[i].min(self.right_blocks.len() - 1) + 1;
+                let covered: Vec<Int> = self.right_blocks.drain(self.right_blocks.len() - k..).collect();
+                self.bottom_segment[id] = self.bottom_segment[covered[0]];
+                self.left_segment[id] = self.left_of.len();
+                self.left_of.push(covered);
+            }
+            self.right_blocks.push(id);
+            self.top_blocks.push(id);
+
+            let x = self.left_of[self.left_segment[id]].iter()
+                .map(|&c| self.placement[c].0 + self.placement[c].2.width)
+                .max()
+                .unwrap_or(0);
+            let y = self.below[self.bottom_segment[id]].iter()
+                .map(|&c| self.placement[c].1 + self.placement[c].2.height)
+                .max()
+                .unwrap_or(0);
+            self.placement[id] = (x, y, self.modules[id]);
+        }
+
+        // compute bounding box
+        self.bounding_box.width = self.placement.iter().map(|(x, _, r)| x + r.width).max().unwrap();
+        self.bounding_box.height = self.placement.iter().map(|(_, y, r)| y + r.height).max().unwrap();
+    }
+}
+
+impl Mutation<CBLMoveType> for CornerBlockList {
+    fn get_random_move(&mut self) -> CBLMoveType {
+        let mut rng: ThreadRng = rand::thread_rng();
+        let n = self.modules.len();
+        let r = if n < 2 { 0 } else { rng.gen_range(0..4) };
+        let move_type: CBLMoveType = match r {
+            0 => CBLMoveType::RotateModule(rng.gen_range(0..n)),
+            1 => {
+                let a = rng.gen_range(0..n);
+                let b = (a + rng.gen_range(1..n)) % n;
+                CBLMoveType::SwapModules(a, b)
+            }
+            2 => CBLMoveType::FlipOrientation(rng.gen_range(1..n)),
+            _ => {
+                let i = rng.gen_range(1..n);
+                let t = rng.gen_range(0..=MAX_JUNCTIONS.min(i - 1));
+                CBLMoveType::ChangeJunctions(i, self.junctions[i], t)
+            }
+        };
+        move_type
+    }
+
+    fn apply_move(&mut self, _move: &CBLMoveType, update: bool) {
+        match *_move {
+            CBLMoveType::RotateModule(a) => self.modules[a] = self.modules[a].transpose(),
+            CBLMoveType::SwapModules(a, b) => self.sequence.swap(a, b),
+            CBLMoveType::FlipOrientation(a) => self.orientation[a] ^= true,
+            CBLMoveType::ChangeJunctions(a, t1, t2) => {
+                self.junctions[a] = if self.junctions[a] == t1 { t2 } else { t1 };
+            }
+        }
+        if update {
+            self.update()
+        }
+    }
+}
+
+impl FloorCost for CornerBlockList {
+    fn get_floor_area(&self) -> f64 {
+        self.current_area
+    }
+
+    fn get_floor_wire(&self) -> f64 {
+        self.current_wire
+    }
+}
+
+impl FloorPlan for CornerBlockList {
+    fn get_floorplan(&self) -> Floorplan {
+        self.placement.clone()
+    }
+}
+
+impl Cost for CornerBlockList {
+    fn get_cost(&self) -> f64 {
+        self.current_cost
+    }
+}
+
+impl Solution<CornerBlockListSolution> for CornerBlockList {
+    fn copy_solution(&self) -> CornerBlockListSolution {
+        (self.sequence.clone(), self.orientation.clone(), self.junctions.clone(), self.modules.clone())
+    }
+
+    fn set_solution(&mut self, solution: CornerBlockListSolution) {
+        (self.sequence, self.orientation, self.junctions, self.modules) = solution;
+        self.update()
+    }
+}
+
+impl RandomSolution<CornerBlockListSolution> for CornerBlockList {
+    fn random_solution(&self) -> CornerBlockListSolution {
+        let mut rng: ThreadRng = rand::thread_rng();
+        let n = self.modules.len();
+        let mut sequence: Vec<Int> = (0..n).collect();
+        sequence.shuffle(&mut rng);
+        let orientation: Vec<bool> = (0..n).map(|_| rng.gen_bool(0.5)).collect();
+        let junctions: Vec<usize> = (0..n)
+            .map(|i| if i == 0 { 0 } else { rng.gen_range(0..=MAX_JUNCTIONS.min(i - 1)) })
+            .collect();
+        let mut rect = self.modules.clone();
+        for r in rect.iter_mut() {
+            if rng.gen_bool(0.5) {
+                *r = r.transpose();
+            }
+        }
+        (sequence, orientation, junctions, rect)
+    }
+}
+
+impl Crossover<CornerBlockListSolution> for CornerBlockList {
+    // order crossover of the block sequence, orientations and T-junctions are taken from the parent of the position
+    fn crossover(&self, a: &CornerBlockListSolution, b: &CornerBlockListSolution) -> CornerBlockListSolution {
+        let n = a.0.len();
+        let (l, r) = random_cut(n);
+        let (sequence, from_a) = order_crossover(&a.0, &b.0, (l, r));
+        let orientation: Vec<bool> = (0..n).map(|i| if l <= i && i < r { a.1[i] } else { b.1[i] }).collect();
+        let junctions: Vec<usize> = (0..n).map(|i| if l <= i && i < r { a.2[i] } else { b.2[i] }).collect();
+        let rect: Vec<Rectangle> = (0..n).map(|i| if from_a[i] { a.3[i] } else { b.3[i] }).collect();
+        (sequence, orientation, junctions, rect)
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compaction::find_overlaps;
+
+    #[test]
+    fn test_pack() {
+        // 1 is inserted right of 0, 2 on top of both
+        let modules = vec![Rectangle::new(2, 2), Rectangle::new(1, 3), Rectangle::new(3, 1)];
+        let mut cbl = CornerBlockList::new(modules.clone(), Vec::new(), 1.0);
+        cbl.set_solution((vec![0, 1, 2], vec![false, false, true], vec![0, 0, 1], modules));
+        assert_eq!(cbl.get_floorplan(), vec![
+            (0, 0, Rectangle::new(2, 2)),
+            (2, 0, Rectangle::new(1, 3)),
+            (0, 3, Rectangle::new(3, 1)),
+        ]);
+        assert_eq!(cbl.get_floor_area(), 12.0);
+    }
+
+    #[test]
+    fn test_moves() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let modules: Vec<Rectangle> = (0..15).map(|_| Rectangle::new(rng.gen_range(1..10), rng.gen_range(1..10))).collect();
+        let mut cbl = CornerBlockList::new(modules, Vec::new(), 1.0);
+        cbl.set_solution(cbl.random_solution());
+        for _ in 0..200 {
+            let _move = cbl.get_random_move();
+            let solution = cbl.copy_solution();
+            cbl.apply_move(&_move, true);
+            assert!(find_overlaps(&cbl.get_floorplan()).is_empty());
+            cbl.apply_move(&_move, false);
+            assert_eq!(cbl.copy_solution(), solution);
+            // keep the move to walk through different lists
+            cbl.apply_move(&_move, true);
+        }
+    }
+}
@@ -0,0 +1,96 @@
+use crate::definitions::*;
+use crate::floorplan_common::*;
+use crate::compaction::get_bounding_box;
+use crate::constraints::PlacementConstraint;
+
+/// deviation of the outline aspect ratio (width / height) from the target,
+/// 0 if the ratio matches, 1 if it is twice or half the target
+#[derive(Debug)]
+pub struct AspectRatioTerm {
+    pub target: f64,
+}
+
+impl CostTerm for AspectRatioTerm {
+    fn evaluate(&self, plan: &Floorplan) -> f64 {
+        let bounding_box = get_bounding_box(plan);
+        let ratio = bounding_box.width as f64 / bounding_box.height as f64;
+        (ratio / self.target).max(self.target / ratio) - 1.0
+    }
+}
+
+/// share of the bounding box not covered by modules
+#[derive(Debug)]
+pub struct WhitespaceTerm {
+    pub module_area: f64,
+}
+
+impl WhitespaceTerm {
+    pub fn new(modules: &[Rectangle]) -> Self {
+        WhitespaceTerm { module_area: modules.iter().map(|rect| rect.area() as f64).sum() }
+    }
+}
+
+impl CostTerm for WhitespaceTerm {
+    fn evaluate(&self, plan: &Floorplan) -> f64 {
+        1.0 - self.module_area / get_bounding_box(plan).area() as f64
+    }
+}
+
+/// gap between two placed modules in x- plus y-direction, 0 if they touch
+pub fn get_gap(a: (Int, Int, Rectangle), b: (Int, Int, Rectangle)) -> Int {
+    let (x1, y1, rect1) = a;
+    let (x2, y2, rect2) = b;
+    let gap_x = x2.saturating_sub(x1 + rect1.width).max(x1.saturating_sub(x2 + rect2.width));
+    let gap_y = y2.saturating_sub(y1 + rect1.height).max(y1.saturating_sub(y2 + rect2.height));
+    gap_x + gap_y
+}
+
+/// pairs of modules that should be placed next to each other
+/// sum of the gaps between the modules of each pair
+#[derive(Debug)]
+pub struct NearTerm {
+    pub pairs: Vec<(usize, usize)>,
+}
+
+impl CostTerm for NearTerm {
+    fn evaluate(&self, plan: &Floorplan) -> f64 {
+        self.pairs.iter().map(|&(a, b)| get_gap(plan[a], plan[b]) as f64).sum()
+    }
+}
+
+/// pairs of modules that should be placed far apart
+/// for each pair the half perimeter of the bounding box minus the distance of the module centers
+#[derive(Debug)]
+pub struct ApartTerm {
+    pub pairs: Vec<(usize, usize)>,
+}
+
+impl CostTerm for ApartTerm {
+    fn evaluate(&self, plan: &Floorplan) -> f64 {
+        let bounding_box = get_bounding_box(plan);
+        let half_perimeter = (bounding_box.width + bounding_box.height) as f64;
+        self.pairs.iter()
+            .map(|&(a, b)| {
+                let (x1, y1, rect1) = plan[a];
+                let (x2, y2, rect2) = plan[b];
+                let (center_x1, center_y1) = rect1.center(x1, y1);
+                let (center_x2, center_y2) = rect2.center(x2, y2);
+                half_perimeter - (center_x1 - center_x2).abs() - (center_y1 - center_y2).abs()
+            })
+            .sum()
+    }
+}
+
+/// sum of the penalties of the hard placement constraints
+/// regions get their own term, since their penalty is an area instead of a distance
+#[derive(Debug)]
+pub struct PlacementConstraintTerm {
+    pub constraints: Vec<PlacementConstraint>,
+}
+
+impl CostTerm for PlacementConstraintTerm {
+    fn evaluate(&self, plan: &Floorplan) -> f64 {
+        self.constraints.iter().map(|c| c.penalty(plan) as f64).sum()
+    }
+}
+
@@ -0,0 +1,174 @@
+use crate::definitions::*;
+use rand::prelude::*;
+
+/// random cut [l, r) with l < r <= n
+pub fn random_cut(n: usize) -> (usize, usize) {
+    let mut rng: ThreadRng = rand::thread_rng();
+    let a = rng.gen_range(0..n);
+    let b = rng.gen_range(0..n);
+    (a.min(b), a.max(b) + 1)
+}
+
+/// order crossover (OX) of two permutations of 0..n
+/// keeps a[l..r] in place and fills the remaining positions with the missing elements in the order of b
+/// returns the child and for each element whether it was inherited from a
+pub fn order_crossover(a: &[Int], b: &[Int], cut: (usize, usize)) -> (Vec<Int>, Vec<bool>) {
+    let n = a.len();
+    let (l, r) = cut;
+    let mut from_a = vec![false; n];
+    for &x in a[l..r].iter() {
+        from_a[x] = true;
+    }
+    let mut child: Vec<Int> = Vec::with_capacity(n);
+    let mut rest = b.iter().filter(|&&x| !from_a[x]);
+    child.extend(rest.by_ref().take(l));
+    child.extend_from_slice(&a[l..r]);
+    child.extend(rest);
+    (child, from_a)
+}
+
+/// partially mapped crossover (PMX) of two permutations of 0..n
+/// keeps a[l..r] in place, the other positions are taken from b and
+/// conflicting elements are replaced by following the mapping a[i] -> b[i] of the cut
+/// returns the child and for each element whether it was inherited from a
+pub fn partially_mapped_crossover(a: &[Int], b: &[Int], cut: (usize, usize)) -> (Vec<Int>, Vec<bool>) {
+    let n = a.len();
+    let (l, r) = cut;
+    let mut from_a = vec![false; n];
+    let mut index_a = vec![0; n];
+    for (i, &x) in a.iter().enumerate() {
+        index_a[x] = i;
+    }
+    for &x in a[l..r].iter() {
+        from_a[x] = true;
+    }
+    let mut child: Vec<Int> = b.to_vec();
+    child[l..r].copy_from_slice(&a[l..r]);
+    for i in (0..l).chain(r..n) {
+        let mut x = b[i];
+        while from_a[x] {
+            x = b[index_a[x]];
+        }
+        child[i] = x;
+    }
+    (child, from_a)
+}
+
+/// cycle crossover (CX) of two permutations of 0..n
+/// the positions are split into the cycles of the mapping a[i] -> b[i],
+/// the cycles are taken alternately from a and b starting with a
+/// returns the child and for each element whether it was inherited from a
+pub fn cycle_crossover(a: &[Int], b: &[Int]) -> (Vec<Int>, Vec<bool>) {
+    let n = a.len();
+    let mut index_a = vec![0; n];
+    for (i, &x) in a.iter().enumerate() {
+        index_a[x] = i;
+    }
+    let mut from_a = vec![false; n];
+    let mut child: Vec<Int> = vec![0; n];
+    let mut visited = vec![false; n];
+    let mut take_a = true;
+    for start in 0..n {
+        if visited[start] {
+            continue;
+        }
+        let mut i = start;
+        while !visited[i] {
+            visited[i] = true;
+            child[i] = if take_a { a[i] } else { b[i] };
+            from_a[child[i]] = take_a;
+            i = index_a[b[i]];
+        }
+        take_a = !take_a;
+    }
+    (child, from_a)
+}
+
+/// permutation crossover operator used by a representation
+#[derive(Debug, Clone, Copy)]
+pub enum CrossoverType {
+    Order(),
+    PartiallyMapped(),
+    Cycle(),
+    Random(), // one of the above for each crossover
+}
+
+impl From<String> for CrossoverType {
+    fn from(s: String) -> Self {
+        match s.as_str() {
+            "order" => CrossoverType::Order(),
+            "pmx" => CrossoverType::PartiallyMapped(),
+            "cycle" => CrossoverType::Cycle(),
+            "random" => CrossoverType::Random(),
+            _ => panic!("unknown crossover type {}", s),
+        }
+    }
+}
+
+impl Default for CrossoverType {
+    fn default() -> Self {
+        CrossoverType::Order()
+    }
+}
+
+impl CrossoverType {
+    /// resolves Random to one of the operators
+    pub fn choose(&self) -> CrossoverType {
+        match self {
+            CrossoverType::Random() => {
+                match rand::thread_rng().gen_range(0..3) {
+                    0 => CrossoverType::Order(),
+                    1 => CrossoverType::PartiallyMapped(),
+                    _ => CrossoverType::Cycle(),
+                }
+            }
+            _ => *self,
+        }
+    }
+
+    /// crossover of two permutations, the cut is ignored by the cycle crossover
+    pub fn apply(&self, a: &[Int], b: &[Int], cut: (usize, usize)) -> (Vec<Int>, Vec<bool>) {
+        match self.choose() {
+            CrossoverType::Order() => order_crossover(a, b, cut),
+            CrossoverType::PartiallyMapped() => partially_mapped_crossover(a, b, cut),
+            CrossoverType::Cycle() => cycle_crossover(a, b),
+            CrossoverType::Random() => unreachable!(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn is_permutation(p: &[Int]) -> bool {
+        let mut seen = vec![false; p.len()];
+        p.iter().all(|&x| x < p.len() && !std::mem::replace(&mut seen[x], true))
+    }
+
+    #[test]
+    fn test_crossovers() {
+        let a: Vec<Int> = vec![0, 1, 2, 3, 4, 5, 6, 7];
+        let b: Vec<Int> = vec![2, 5, 0, 7, 1, 3, 6, 4];
+
+        let (child, from_a) = order_crossover(&a, &b, (3, 6));
+        assert_eq!(child, vec![2, 0, 7, 3, 4, 5, 1, 6]);
+        assert_eq!(from_a, vec![false, false, false, true, true, true, false, false]);
+
+        let (child, from_a) = partially_mapped_crossover(&a, &b, (3, 6));
+        assert_eq!(child, vec![2, 7, 0, 3, 4, 5, 6, 1]);
+        assert_eq!(from_a, vec![false, false, false, true, true, true, false, false]);
+
+        // cycles of positions: {0, 2}, {1, 5, 3, 7, 4}, {6}
+        let (child, from_a) = cycle_crossover(&a, &b);
+        assert_eq!(child, vec![0, 5, 2, 7, 1, 3, 6, 4]);
+        assert_eq!(from_a, vec![true, false, true, false, false, false, true, false]);
+
+        for _ in 0..100 {
+            let cut = random_cut(a.len());
+            for crossover_type in [CrossoverType::Order(), CrossoverType::PartiallyMapped(), CrossoverType::Cycle()] {
+                assert!(is_permutation(&crossover_type.apply(&a, &b, cut).0));
+            }
+        }
+    }
+}
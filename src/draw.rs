@@ -10,6 +10,7 @@ const RECT_COLOR: RGB = RGB::new(65,105,225);
 const BACKGROUND_COLOR: RGB = RGB::new(128, 128, 128);
 const STROKE_COLOR: RGB = RGB::new(0, 0, 0);
 const LINE_COLOR: RGB = RGB::new(220,20,60);
+const REGION_COLOR: RGB = RGB::new(255,165,0);
 const LINE_WIDTH: u32 = 1;
 
 fn create_rectangle(x: f32, y: f32, width: u32, height: u32, fill: RGB) -> Drawing {
@@ -62,9 +63,23 @@ fn create_line_from_net(plan: &Floorplan, net: &Net, canvas_height: u32) -> Draw
     line_drawing
 }
 
-fn compute_canvas_size(plan: &Floorplan) -> (u32, u32) {
+// outline of a region, regions are drawn above the modules
+fn create_region(x: f32, y: f32, width: u32, height: u32) -> Drawing {
+    let mut rect = Drawing::new(Shape::Rectangle { width, height });
+    rect.position.x = x;
+    rect.position.y = y;
+    rect.style = Style {
+        fill: None,
+        stroke: Some(Stroke{color: REGION_COLOR, width: 2 * LINE_WIDTH}),
+    };
+    rect
+}
+
+fn compute_canvas_size(plan: &Floorplan, regions: &[(usize, Int, Int, Rectangle)]) -> (u32, u32) {
     let (max_x, max_y) : (usize, usize) = plan
     .iter()
+    .map(|(x, y, rect)| (x, y, rect))
+    .chain(regions.iter().map(|(_, x, y, rect)| (x, y, rect)))
     .fold((0,0),
      |(acc_x, acc_y), (x, y, rect) | 
      (acc_x.max(x + rect.width),  acc_y.max(y + rect.height))
@@ -72,8 +87,8 @@ fn compute_canvas_size(plan: &Floorplan) -> (u32, u32) {
     (max_x as u32, max_y as u32)
 }
 
-pub fn draw_floorplan(plan: &Floorplan, file: &str, net_list: &Vec<Net>, draw_nets: bool) {
-    let (canvas_width, canvas_height) = compute_canvas_size(&plan);
+pub fn draw_floorplan(plan: &Floorplan, file: &str, net_list: &[Net], draw_nets: bool, regions: &[(usize, Int, Int, Rectangle)]) {
+    let (canvas_width, canvas_height) = compute_canvas_size(plan, regions);
     let mut canvas = Canvas::new(canvas_width, canvas_height);
     
     // add background
@@ -88,6 +103,12 @@ pub fn draw_floorplan(plan: &Floorplan, file: &str, net_list: &Vec<Net>, draw_ne
         canvas.display_list.add(rect);
     }
 
+    // add regions
+    for (_, x, y, region) in regions {
+        let y = canvas_height as f32 - *y as f32 - region.height as f32;
+        canvas.display_list.add(create_region(*x as f32, y, region.width as u32, region.height as u32));
+    }
+
     // add nets
     if draw_nets {
         for net in net_list.iter() {
@@ -1,9 +1,12 @@
 
-use crate::{definitions::*, hypergraph::Hypergraph};
+use std::fmt::Debug;
+use crate::{definitions::*, hypergraph::Hypergraph, constraints::Constraints, partitioning::GainBuckets};
 
 pub struct  FloorplanInstance {
     pub modules: Vec<Rectangle>,
     pub nets: Vec<Net>,
+    pub constraints: Constraints,
+    pub optimum: Option<Rectangle>, // known optimal bounding box of generated instances
 }
 
 pub trait Mutation<Move> {
@@ -32,34 +35,77 @@ pub trait RandomSolution<T: Clone> {
 }
 pub trait Solution<T: Clone> {
     fn copy_solution(&self) -> T;
+    /// keeps the averages of the cost function, so costs of different solutions stay comparable
+    /// initial solutions re-estimate them explicitly by update_cost_function
     fn set_solution(&mut self, solution: T);
 }
 
+/// additional term of the cost function, lower values are better
+pub trait CostTerm: Debug {
+    fn evaluate(&self, plan: &Floorplan) -> f64;
+}
+
+#[derive(Debug)]
+pub struct WeightedCostTerm {
+    pub term: Box<dyn CostTerm>,
+    pub weight: f64,
+    avg: f64,
+}
+
+impl WeightedCostTerm {
+    pub fn new(term: Box<dyn CostTerm>, weight: f64) -> Self {
+        WeightedCostTerm { term, weight, avg: 1.0 }
+    }
+}
+
 #[derive(Debug)]
 pub struct CostFunction {
     pub alpha: f64,
     avg_wirelength: f64,
     avg_area: f64,
+    terms: Vec<WeightedCostTerm>,
 }
 
 impl Default for CostFunction {
     fn default() -> Self {
-        Self { alpha: 1.0, avg_wirelength: 1.0, avg_area: 1.0 }
+        Self { alpha: 1.0, avg_wirelength: 1.0, avg_area: 1.0, terms: Vec::new() }
     }
 }
 
 impl CostFunction {
     pub fn new(alpha: f64, avg_wirelength: f64, avg_area: f64) -> Self {
-        CostFunction { alpha, avg_wirelength, avg_area }
+        CostFunction { alpha, avg_wirelength, avg_area, terms: Vec::new() }
+    }
+
+    pub fn set_terms(&mut self, terms: Vec<WeightedCostTerm>) {
+        self.terms = terms;
     }
 
     /// computes the cost of a floorplan
-    pub fn get_cost(&self, area: f64, wirelength: f64) -> f64 {
+    /// each additional term is normalized by its average and weighted
+    pub fn get_cost(&self, area: f64, wirelength: f64, plan: &Floorplan) -> f64 {
         let area_cost = area / self.avg_area;
         let wire_cost = wirelength / self.avg_wirelength;
-        let cost = area_cost * self.alpha + wire_cost * (1.0 - self.alpha);
+        let mut cost = area_cost * self.alpha + wire_cost * (1.0 - self.alpha);
+        for t in self.terms.iter() {
+            cost += t.weight * t.term.evaluate(plan) / t.avg;
+        }
         cost
     }
+
+    pub fn has_terms(&self) -> bool {
+        !self.terms.is_empty()
+    }
+
+    /// sets the averages of area, wirelength and all terms from perturbation samples
+    pub fn update_averages(&mut self, samples: &[PerturbationSample]) {
+        let (avg_area, avg_wirelength, avg_terms) = self.compute_mean_parameters(samples);
+        self.avg_area = avg_area;
+        self.avg_wirelength = avg_wirelength;
+        for (t, avg) in self.terms.iter_mut().zip(avg_terms) {
+            t.avg = avg;
+        }
+    }
     
     /// computes the total used wirelength using half-perimenter estimation
     pub fn compute_wirelength(plan: &Floorplan, nets: &Vec<Net>) -> f64 {
@@ -88,19 +134,20 @@ impl CostFunction {
         }
         total_wirelength
     }
-    /// estimates avg-area and -wirelength by perturbation for the cost function
-    pub fn compute_mean_parameters<T: Mutation<Move> + FloorCost, Move>(algo: &mut T, repetitions: usize) -> (f64, f64) {
+    /// estimates avg-area, -wirelength and the averages of the additional terms from perturbation samples for the cost function
+    pub fn compute_mean_parameters(&self, samples: &[PerturbationSample]) -> (f64, f64, Vec<f64>) {
+        let repetitions = samples.len();
         let mut sum_area = 0.0;
         let mut sum_wirelength = 0.0;
-        for _ in 0..repetitions {
-            let _move: Move = algo.get_random_move();
-            algo.apply_move(&_move, true);
-            let wire = algo.get_floor_wire();
-            let area = algo.get_floor_area();
+        let mut sum_terms = vec![0.0; self.terms.len()];
+        for (area, wire, plan) in samples.iter() {
             sum_area += area;
             sum_wirelength += wire;
-            algo.apply_move(&_move, false);
-            
+            if let Some(plan) = plan {
+                for (sum, t) in sum_terms.iter_mut().zip(self.terms.iter()) {
+                    *sum += t.term.evaluate(plan);
+                }
+            }
         }
         if sum_wirelength == 0.0 {
             sum_wirelength = repetitions as f64;
@@ -108,7 +155,9 @@ impl CostFunction {
         if sum_area == 0.0 {
             sum_area = repetitions as f64
         }
-        (sum_area / repetitions as f64, sum_wirelength / repetitions as f64)
+        // terms that vanish for all samples are not normalized
+        let avg_terms = sum_terms.iter().map(|&sum| if sum == 0.0 { 1.0 } else { sum / repetitions as f64 }).collect();
+        (sum_area / repetitions as f64, sum_wirelength / repetitions as f64, avg_terms)
     }
 
     /// returns percentage of area not covered by boxes
@@ -122,40 +171,79 @@ impl CostFunction {
     }
 }
 
+/// raw area, wirelength and, if requested, floorplan after a random perturbation
+pub type PerturbationSample = (f64, f64, Option<Floorplan>);
+
+/// applies random perturbations and samples the raw area and wirelength independent of the cost function
+/// each perturbation is reversed without update
+pub fn sample_perturbations<T: Mutation<Move> + FloorCost + FloorPlan, Move>(algo: &mut T, repetitions: usize, keep_plans: bool) -> Vec<PerturbationSample> {
+    let mut samples: Vec<PerturbationSample> = Vec::with_capacity(repetitions);
+    for _ in 0..repetitions {
+        let _move: Move = algo.get_random_move();
+        algo.apply_move(&_move, true);
+        let plan = if keep_plans { Some(algo.get_floorplan()) } else { None };
+        samples.push((algo.get_floor_area(), algo.get_floor_wire(), plan));
+        algo.apply_move(&_move, false);
+    }
+    samples
+}
+
 /// greedy method to generate a linear ordering of the modules reducing wirelength
 /// reduced wirelength a bit, but increase area in experiments
-#[allow(dead_code)]
-pub fn cluster_growing_order(graph: &Hypergraph, start_node: Int) -> Vec<Int> {
-    let mut order: Vec<Int> = vec![start_node];
-    let mut placed_nodes: Vec<bool> = vec![false; graph.num_nodes];
-    placed_nodes[start_node] = true;
-
-    // place node with highest gain
-    // gain = terminating nets - new nets
-    for _ in 0..graph.num_nodes - 1 {
-        let mut best_gain: i32 = -i32::MAX;
-        let mut best_node: Int = 0;
-        for v in 0..graph.num_nodes {
-            if !placed_nodes[v] {
-                let mut terminating_nets = 0; 
-                let mut new_nets = 0; 
-                for net in graph.out_nets[v].iter() {
-                    terminating_nets += net.pins.iter().all(|w| placed_nodes[*w]) as i32;
-                    new_nets += net.pins.iter().all(|w| !placed_nodes[*w]) as i32;
-                }
-                let gain = terminating_nets - new_nets;
-                if gain > best_gain {
-                    best_gain = gain;
-                    best_node = v;
+/// the unplaced node of highest gain = terminating nets - new nets is appended, ties are broken by larger area,
+/// gains are kept in buckets and updated incrementally for the pins of the nets of each placed node
+pub fn cluster_growing_order(graph: &Hypergraph, start_node: Int, modules: &[Rectangle]) -> Vec<Int> {
+    let n = graph.num_nodes;
+    let max_degree = (0..n).map(|v| graph.degree(v)).max().unwrap_or(0);
+    let mut placed_pins: Vec<usize> = vec![0; graph.num_nets];
+    let mut buckets = GainBuckets::new(n, max_degree);
+    // initially all nets with other pins are new nets
+    for v in (0..n).filter(|&v| v != start_node) {
+        let gain = graph.nets(v).iter().filter(|&&e| graph.pins(e).len() > 1).count() as i64;
+        buckets.insert(v, -gain);
+    }
+
+    let mut order: Vec<Int> = Vec::with_capacity(n);
+    let mut next = Some(start_node);
+    while let Some(u) = next {
+        order.push(u);
+        for &e in graph.nets(u).iter() {
+            let pins = graph.pins(e);
+            let placed = placed_pins[e];
+            // the net is no longer new and terminates if only one pin is left unplaced
+            let delta = (placed == 0) as i64 + (placed + 2 == pins.len()) as i64;
+            placed_pins[e] += 1;
+            for &w in pins.iter() {
+                if delta != 0 && buckets.contains(w) {
+                    buckets.update(w, delta);
                 }
             }
         }
-        placed_nodes[best_node] = true;
-        order.push(best_node);
+        next = buckets.top_bucket().iter().copied().max_by_key(|&v| (modules[v].area(), std::cmp::Reverse(v)));
+        if let Some(v) = next {
+            buckets.remove(v);
+        }
     }
     order
 }
 
+/// node with the most nets, e.g. as start node of the cluster growing order
+pub fn most_connected_node(graph: &Hypergraph) -> Int {
+    (0..graph.num_nodes).max_by_key(|&v| (graph.degree(v), std::cmp::Reverse(v))).unwrap_or(0)
+}
+
+/// splits a region into two parts, the first with the given share of the area, by a cut perpendicular to the longer side
+/// returns whether the parts are side by side and both regions
+pub fn split_region(region: (f64, f64), share: f64) -> (bool, (f64, f64), (f64, f64)) {
+    let (width, height) = region;
+    if width >= height {
+        (true, (width * share, height), (width * (1.0 - share), height))
+    }
+    else {
+        (false, (width, height * share), (width, height * (1.0 - share)))
+    }
+}
+
 /// reorders a vector according to a given permutation
 #[allow(dead_code)]
 pub fn reorder_vec<T: Clone>(permutation: &Vec<usize>, vec: &Vec<T>) -> Vec<T> {
@@ -180,8 +268,15 @@ mod tests {
             Net{pins: vec![2, 3, 4], id: 4},
             Net{pins: vec![3, 4],    id: 5},
         ];
-        let graph = Hypergraph::from(nets);
-        let order = cluster_growing_order(&graph, 0);
+        let graph = Hypergraph::new(5, &nets);
+        let mut modules = vec![Rectangle::new(1, 1); 5];
+        let order = cluster_growing_order(&graph, 0, &modules);
         assert_eq!(order, vec![0, 1, 3, 4, 2]);
+        assert_eq!(most_connected_node(&graph), 3);
+
+        // 1 and 2 have the same gain after 0
+        let graph = Hypergraph::new(3, &[Net{pins: vec![0, 1], id: 0}, Net{pins: vec![0, 2], id: 1}]);
+        modules[2] = Rectangle::new(2, 2);
+        assert_eq!(cluster_growing_order(&graph, 0, &modules), vec![0, 2, 1]);
     }
 }
\ No newline at end of file
@@ -1,14 +1,25 @@
+use std::fmt::Debug;
 use std::marker::PhantomData;
 
 use crate::floorplan_common::*;
+use crate::simulated_annealing::SimulatedAnnealing;
 use rand::{prelude::*, distributions::WeightedIndex};
 
+// probability that a worse move is accepted at the start of the local search
+const LOCAL_SEARCH_PROB: f64 = 0.05;
+// number of moves to estimate the local search temperature
+const LOCAL_SEARCH_MOVES_ESTIMATION: usize = 100;
+// offspring whose relative cost difference to a member of the population is below are duplicates
+const DUPLICATE_TOLERANCE: f64 = 1e-9;
+
 pub struct GeneticAlgorithmConfig {
     pub mutation_rate: f64,
     pub population_size: usize,
     pub generations: usize,
+    pub elite_size: usize,              // best solutions copied unchanged into the next population
+    pub local_search_iterations: usize, // SA iterations for each offspring, 0 disables the memetic local search
 }
-pub struct GeneticAlgorithm<T, S, Move> 
+pub struct GeneticAlgorithm<T, S, Move>
 where
     T: RandomSolution<S>,
     S: Clone
@@ -16,21 +27,22 @@ where
     config: GeneticAlgorithmConfig,
     population: Vec<S>,         // population is the set of solutions
     next_population: Vec<S>,    // buffer for the next population
-    cost: Vec<f64>,          
-    next_cost: Vec<f64>,     
-    fitness: Vec<f64>,     
-    index: usize,  
+    cost: Vec<f64>,
+    next_cost: Vec<f64>,
+    fitness: Vec<f64>,
+    index: usize,
     rng: ThreadRng,
     weighted_index: WeightedIndex<f64>, // to generate weigthed probabilities
+    local_search: Option<SimulatedAnnealing>,
     phantom1: PhantomData<T>,    // to use type parameter T in implementation
-    phantom2: PhantomData<Move>,   
+    phantom2: PhantomData<Move>,
 }
 
-impl<T, S, Move> GeneticAlgorithm<T, S, Move> 
-where 
+impl<T, S, Move> GeneticAlgorithm<T, S, Move>
+where
     T: Cost + Solution<S> + RandomSolution<S> + Mutation<Move> + Crossover<S>,
-    S: Clone + Default
-    
+    S: Clone + Debug + Default
+
 {
     pub fn new(config: GeneticAlgorithmConfig) -> Self {
         let population: Vec<S> = vec![S::default(); config.population_size];
@@ -40,29 +52,101 @@ where
         let fitness = cost.clone();
         let index = 0;
         let rng = rand::thread_rng();
-        let weighted_index = WeightedIndex::new(&fitness).unwrap();
+        let weighted_index = Self::weighted_index(&fitness);
+        let local_search = None;
         let phantom1 = PhantomData;
         let phantom2 = PhantomData;
-        GeneticAlgorithm { config, population, next_population, cost, next_cost, fitness, index, rng, weighted_index, phantom1, phantom2 }
+        GeneticAlgorithm { config, population, next_population, cost, next_cost, fitness, index, rng, weighted_index, local_search, phantom1, phantom2 }
+    }
+
+    // short low temperature SA run, the temperature is estimated once from the initial solution
+    fn initialize_local_search(&mut self, instance: &mut T) {
+        let iterations = self.config.local_search_iterations;
+        if iterations == 0 {
+            return;
+        }
+        let temperature = SimulatedAnnealing::estimate_initial_temperature(LOCAL_SEARCH_PROB, LOCAL_SEARCH_MOVES_ESTIMATION, instance);
+        let decay = SimulatedAnnealing::get_decay_for_n_iterations(iterations, temperature);
+        eprintln!("local search T: {}, it: {}, decay: {}", temperature, iterations, decay);
+        let mut sa = SimulatedAnnealing::new(iterations, temperature, decay);
+        sa.set_verbose(false);
+        self.local_search = Some(sa);
     }
 
+    // the current solution of the instance is kept as first member
     fn initialize_populuation(&mut self, instance: &mut T) {
-        for i in 0..self.config.population_size {
+        self.population[0] = instance.copy_solution();
+        self.cost[0] = instance.get_cost();
+        for i in 1..self.config.population_size {
             let solution = instance.random_solution();
-            // TODO remove unnecessary copy
-            instance.set_solution(solution.clone());
+            instance.set_solution(solution);
+            if let Some(sa) = &self.local_search {
+                sa.run(instance);
+            }
             self.cost[i] = instance.get_cost();
-            self.population[i] = solution;
+            self.population[i] = instance.copy_solution();
         }
     }
 
     fn fitness_function(&self, x: f64) -> f64 {
        1.0 /  x.exp()
     }
-    
+
+    // costs are shifted by the minimum, the best member has fitness 1 and exp(-cost) does not underflow for all members
     fn compute_fitness(&mut self){
+        let min_cost = self.cost.iter().copied().fold(f64::INFINITY, f64::min);
         for i in 0..self.config.population_size {
-            self.fitness[i] = self.fitness_function(self.cost[i]);
+            self.fitness[i] = self.fitness_function(self.cost[i] - min_cost);
+        }
+    }
+
+    // falls back to uniform selection if the weights are invalid, e.g. all zero
+    fn weighted_index(fitness: &[f64]) -> WeightedIndex<f64> {
+        WeightedIndex::new(fitness).unwrap_or_else(|_| WeightedIndex::new(vec![1.0; fitness.len()]).unwrap())
+    }
+
+    // two different members, the second is drawn from the remaining ones
+    fn select_parents(&mut self) -> (usize, usize) {
+        let n = self.config.population_size;
+        let i = self.weighted_index.sample(&mut self.rng);
+        let mut fitness = self.fitness.clone();
+        fitness[i] = 0.0;
+        let j = match WeightedIndex::new(&fitness) {
+            Ok(weighted_index) => weighted_index.sample(&mut self.rng),
+            Err(_) => (i + self.rng.gen_range(1..n)) % n,
+        };
+        (i, j)
+    }
+
+    fn is_duplicate(&self, cost: f64) -> bool {
+        self.next_cost[..self.index].iter().any(|&c| (c - cost).abs() <= DUPLICATE_TOLERANCE * cost.abs())
+    }
+
+    // improves the offspring set in the instance by local search and adds it to the next population
+    // duplicates are replaced by random solutions to preserve diversity
+    fn insert_offspring(&mut self, instance: &mut T) {
+        if let Some(sa) = &self.local_search {
+            sa.run(instance);
+        }
+        if self.is_duplicate(instance.get_cost()) {
+            let solution = instance.random_solution();
+            instance.set_solution(solution);
+            if let Some(sa) = &self.local_search {
+                sa.run(instance);
+            }
+        }
+        self.next_population[self.index] = instance.copy_solution();
+        self.next_cost[self.index] = instance.get_cost();
+        self.index += 1;
+    }
+
+    fn perform_elitism(&mut self, elites: usize) {
+        let mut order: Vec<usize> = (0..self.config.population_size).collect();
+        order.sort_by(|&i, &j| self.cost[i].total_cmp(&self.cost[j]));
+        for &i in order[..elites].iter() {
+            self.next_population[self.index] = self.population[i].clone();
+            self.next_cost[self.index] = self.cost[i];
+            self.index += 1;
         }
     }
 
@@ -73,57 +157,51 @@ where
             // TODO avoid unncessary update in instance
             instance.set_solution(self.population[i].clone());
             let _move = instance.get_random_move();
-            instance.apply_move(&_move);
-
-            let solution = instance.copy_solution();
-            let cost = instance.get_cost();
-            
-            self.next_population[self.index] = solution;
-            self.next_cost[self.index] = cost;
-            self.index += 1;
+            instance.apply_move(&_move, true);
+            self.insert_offspring(instance);
         }
     }
 
     fn perform_crossover(&mut self, instance: &mut T, crossovers: usize) {
         for _ in 0..crossovers {
-            let mut i: usize;
-            let mut j: usize;
-            loop {
-                i = self.weighted_index.sample(&mut self.rng);
-                j = self.weighted_index.sample(&mut self.rng);
-                if i != j {
-                    break;
-                }
-            }
+            let (i, j) = self.select_parents();
             let a = &self.population[i];
             let b = &self.population[j];
 
             let solution = instance.crossover(a, b);
             // TODO remove unnecessary update in set solution
-            instance.set_solution(solution.clone());
-            let cost = instance.get_cost();
-            
-            self.next_population[self.index] = solution;
-            self.next_cost[self.index] = cost;
-            self.index += 1;
+            instance.set_solution(solution);
+            self.insert_offspring(instance);
         }
     }
 
+    /// costs of all members are compared under the cost normalization of the instance when the run starts,
+    /// set_solution does not re-estimate it
     pub fn run(&mut self, instance: &mut T) {
+        self.initialize_local_search(instance);
         self.initialize_populuation(instance);
-        let mutations = (self.config.mutation_rate * self.config.population_size as f64).round() as usize;
-        let crossovers = self.config.population_size - mutations;
+        let elites = self.config.elite_size.min(self.config.population_size);
+        let mutations = ((self.config.mutation_rate * self.config.population_size as f64).round() as usize)
+            .min(self.config.population_size - elites);
+        let crossovers = self.config.population_size - elites - mutations;
+
+        let mut best_solution: S = self.population[0].clone();
+        let mut best_cost: f64 = self.cost[0];
+        for i in 1..self.config.population_size {
+            if self.cost[i] < best_cost {
+                best_cost = self.cost[i];
+                best_solution = self.population[i].clone();
+            }
+        }
 
-        let mut best_solution: S = instance.copy_solution();
-        let mut best_cost: f64 = instance.get_cost();
-    
         for gen in 0..self.config.generations {
             self.index = 0;
             self.compute_fitness();
-            self.weighted_index = WeightedIndex::new(&self.fitness).unwrap();
+            self.weighted_index = Self::weighted_index(&self.fitness);
 
-            self.perform_mutations(instance, mutations);   
-            self.perform_crossover(instance, crossovers);   
+            self.perform_elitism(elites);
+            self.perform_mutations(instance, mutations);
+            self.perform_crossover(instance, crossovers);
 
             for i in 0..self.config.population_size {
                 if self.next_cost[i] < best_cost {
@@ -132,7 +210,7 @@ where
                 }
             }
             if gen % (self.config.generations.div_ceil(10)) == 0 {
-                println!("gen {}, best {:.2}, best this gen {}", gen, best_cost, self.next_cost.iter().map(|x| (x * 1000.0) as i32).min().unwrap());
+                eprintln!("gen {}, best {:.2}, best this gen {}", gen, best_cost, self.next_cost.iter().map(|x| (x * 1000.0) as i32).min().unwrap());
             }
 
             std::mem::swap(&mut self.population, &mut self.next_population);
@@ -143,18 +221,47 @@ where
 
     }
 }
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::definitions::*;
+    use crate::sequence_pair::*;
 
-// slower and worse quailty than GA
-fn run_genetic_algorithm<T, S, Move>(p: &mut T) 
-where 
-    T: Cost + Solution<S> + RandomSolution<S> + Mutation<Move> + Crossover<S>,
-    S: Clone + Default
-{
-    let mutation_rate = 0.05;
-    let generations = 10_000;
-    let population_size = 100;
-    println!("mutation rate: {}, generations: {}, population size {}: ", mutation_rate, generations, population_size);
-    let config: GeneticAlgorithmConfig = GeneticAlgorithmConfig{mutation_rate, generations, population_size};
-    let mut ga = GeneticAlgorithm::new(config);
-    ga.run(p);
-}
\ No newline at end of file
+    type SequencePairGA = GeneticAlgorithm<SequencePair, SequencePairSolution, SPMoveType>;
+
+    fn config(population_size: usize, elite_size: usize) -> GeneticAlgorithmConfig {
+        GeneticAlgorithmConfig { mutation_rate: 0.3, population_size, generations: 10, elite_size, local_search_iterations: 0 }
+    }
+
+    #[test]
+    fn test_selection() {
+        let mut ga = SequencePairGA::new(config(4, 0));
+        // exp(-cost) underflows to zero for all members
+        ga.cost = vec![800.0, 2000.0, 3000.0, 5000.0];
+        ga.compute_fitness();
+        assert_eq!(ga.fitness, vec![1.0, 0.0, 0.0, 0.0]);
+        ga.weighted_index = SequencePairGA::weighted_index(&ga.fitness);
+        for _ in 0..100 {
+            let (i, j) = ga.select_parents();
+            assert_eq!(i, 0);
+            assert_ne!(j, 0);
+        }
+        // invalid weights fall back to uniform selection
+        ga.fitness = vec![0.0; 4];
+        ga.weighted_index = SequencePairGA::weighted_index(&ga.fitness);
+        let (i, j) = ga.select_parents();
+        assert_ne!(i, j);
+    }
+
+    #[test]
+    fn test_run() {
+        let modules: Vec<Rectangle> = (1..=8).map(|i| Rectangle::new(i, 9 - i)).collect();
+        let nets = vec![Net::new(vec![0, 7], 0), Net::new(vec![1, 2, 3], 1)];
+        let mut sp = SequencePair::new(modules, nets, 0.5);
+        let initial_cost = sp.get_cost();
+        let mut ga = SequencePairGA::new(config(10, 1));
+        ga.run(&mut sp);
+        // the initial solution is a member and the best member survives
+        assert!(sp.get_cost() <= initial_cost);
+    }
+}
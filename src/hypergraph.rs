@@ -1,30 +1,80 @@
 use crate::definitions::*;
 
+/// nets of the modules in compressed sparse row format,
+/// modules that are not connected to any net are nodes without nets
 #[derive(Debug)]
 pub struct Hypergraph {
-    pub out_nets: Vec<Vec<Net>>,
     pub num_nodes: Int,
     pub num_nets: Int,
+    node_offsets: Vec<usize>, // nets of node v are node_nets[node_offsets[v]..node_offsets[v + 1]]
+    node_nets: Vec<usize>,
+    net_offsets: Vec<usize>,  // pins of net e are net_pins[net_offsets[e]..net_offsets[e + 1]]
+    net_pins: Vec<Int>,
 }
 
-impl From<Vec<Net>> for Hypergraph {
-    fn from(net_list: Vec<Net>) -> Self {
-        // determine number of nodes
-        let num_nodes = 1 + net_list.iter().map(|net: &Net| net.pins.iter().max().unwrap()).max().unwrap();
+impl Hypergraph {
+    /// hypergraph of num_nodes modules, net e is the e-th net of the list
+    pub fn new(num_nodes: Int, net_list: &[Net]) -> Self {
         let num_nets = net_list.len();
-        let mut out_nets: Vec<Vec<Net>> = vec![Vec::new(); num_nodes];
-        
-        // for each net e add an outgoing net e' for each pin contained in e
+        let mut net_offsets: Vec<usize> = Vec::with_capacity(num_nets + 1);
+        let mut net_pins: Vec<Int> = Vec::new();
+        let mut degree: Vec<usize> = vec![0; num_nodes];
+        net_offsets.push(0);
         for net in net_list.iter() {
-            for i in 0..net.pins.len() {
-                let v = net.pins[i];
-                let mut e = net.pins.clone();
+            for &v in net.pins.iter() {
+                if v >= num_nodes {
+                    panic!("net {} contains unknown module {}", net.id, v);
+                }
+                degree[v] += 1;
+            }
+            net_pins.extend(net.pins.iter());
+            net_offsets.push(net_pins.len());
+        }
 
-                // remove v from net
-                e.remove(i);
-                out_nets[v].push(Net::new(e, net.id));
+        // counting sort of the pins by node
+        let mut node_offsets: Vec<usize> = vec![0; num_nodes + 1];
+        for v in 0..num_nodes {
+            node_offsets[v + 1] = node_offsets[v] + degree[v];
+        }
+        let mut next: Vec<usize> = node_offsets[..num_nodes].to_vec();
+        let mut node_nets: Vec<usize> = vec![0; net_pins.len()];
+        for e in 0..num_nets {
+            for &v in net_pins[net_offsets[e]..net_offsets[e + 1]].iter() {
+                node_nets[next[v]] = e;
+                next[v] += 1;
             }
         }
-        Hypergraph{out_nets, num_nodes, num_nets}
+        Hypergraph{num_nodes, num_nets, node_offsets, node_nets, net_offsets, net_pins}
+    }
+
+    /// nets containing node v
+    pub fn nets(&self, v: Int) -> &[usize] {
+        &self.node_nets[self.node_offsets[v]..self.node_offsets[v + 1]]
+    }
+
+    /// pins of net e
+    pub fn pins(&self, e: usize) -> &[Int] {
+        &self.net_pins[self.net_offsets[e]..self.net_offsets[e + 1]]
     }
-}
\ No newline at end of file
+
+    pub fn degree(&self, v: Int) -> usize {
+        self.node_offsets[v + 1] - self.node_offsets[v]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hypergraph() {
+        let nets = vec![Net::new(vec![0, 2], 0), Net::new(vec![2, 3, 0], 1)];
+        let graph = Hypergraph::new(5, &nets);
+        assert_eq!(graph.nets(0), &[0, 1]);
+        assert_eq!(graph.nets(1), &[] as &[usize]);
+        assert_eq!(graph.nets(2), &[0, 1]);
+        assert_eq!(graph.pins(1), &[2, 3, 0]);
+        assert_eq!(graph.degree(4), 0);
+        assert_eq!(Hypergraph::new(3, &[]).degree(2), 0);
+    }
+}
@@ -1,22 +1,258 @@
-use rand::distributions::{Distribution, Uniform};
+use rand::prelude::*;
+use rand::rngs::StdRng;
 use crate::definitions::*;
 
-#[allow(dead_code)]
-pub fn random_instance(num_modules: Int, num_nets: Int, min_size: Int, max_size: Int) -> (Vec<Rectangle>, Vec<Net>) {
-    let mut rng = rand::thread_rng();
-
-    let range = Uniform::<Int>::from(min_size..max_size);
-    let modules: Vec<Rectangle> = 
-    (0..num_modules)
-    .map(|_| Rectangle::new(range.sample(&mut rng), range.sample(&mut rng)))
-    .collect();
-    
-    // only nets with two pins for now
-    let range = Uniform::<Int>::from(0..num_modules);
-    let nets: Vec<Net> = 
-            (0..num_nets)
-            .map(|i| Net{pins: vec![range.sample(&mut rng), range.sample(&mut rng)], id: i})
-            .collect();
+// macros are this many times larger than the largest module in each dimension, at least half of it
+const MACRO_FACTOR: Int = 4;
+// probability that a part of a non-guillotine dissection is split into a pinwheel instead of being cut
+const PINWHEEL_PROB: f64 = 0.3;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SizeDistribution {
+    Uniform(),
+    LogNormal(), // median at the geometric mean of the size range, 95% of the lengths inside the range
+}
+
+impl From<String> for SizeDistribution {
+    fn from(s: String) -> Self {
+        match s.as_str() {
+            "uniform" => SizeDistribution::Uniform(),
+            "lognormal" => SizeDistribution::LogNormal(),
+            _ => panic!("unsupported size distribution {}", s),
+        }
+    }
+}
+
+/// dissection of a rectangle into modules, the instance has a floorplan without dead area
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Dissection {
+    Guillotine(),    // recursive cuts through a whole part
+    NonGuillotine(), // cuts and pinwheels of five modules, starting with a pinwheel
+}
+
+impl From<String> for Dissection {
+    fn from(s: String) -> Self {
+        match s.as_str() {
+            "guillotine" => Dissection::Guillotine(),
+            "non_guillotine" => Dissection::NonGuillotine(),
+            _ => panic!("unsupported dissection {}", s),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct GeneratorConfig {
+    pub num_modules: usize,
+    pub num_nets: usize,
+    pub seed: u64,
+    pub size_distribution: SizeDistribution,
+    pub min_size: Int,
+    pub max_size: Int,
+    pub num_macros: usize,
+    pub degree_exponent: f64, // P(degree = d) ~ d^-degree_exponent for 2 <= d <= max_degree
+    pub max_degree: usize,
+    pub locality: f64, // probability that a pin is taken from the neighborhood of the first pin of its net
+}
+
+fn sample_length(rng: &mut StdRng, distribution: SizeDistribution, min_size: Int, max_size: Int) -> Int {
+    match distribution {
+        SizeDistribution::Uniform() => rng.gen_range(min_size..=max_size),
+        SizeDistribution::LogNormal() => {
+            let (low, high) = ((min_size as f64).ln(), (max_size as f64).ln());
+            // Box-Muller transform of two uniform samples
+            let normal = (-2.0 * (1.0 - rng.gen::<f64>()).ln()).sqrt() * (2.0 * std::f64::consts::PI * rng.gen::<f64>()).cos();
+            let length = ((low + high) / 2.0 + normal * (high - low) / 4.0).exp().round() as Int;
+            length.clamp(min_size, max_size)
+        }
+    }
+}
+
+// degree of a net from the truncated power law, as observed for netlists following Rent's rule
+fn sample_degree(rng: &mut StdRng, cumulative: &[f64]) -> usize {
+    let r = rng.gen::<f64>() * cumulative.last().unwrap();
+    2 + cumulative.iter().position(|&c| r < c).unwrap_or(cumulative.len() - 1)
+}
+
+/// random instance that only depends on the configuration and its seed,
+/// the modules are arranged on a virtual grid by their id, local pins are taken near the first pin of a net
+pub fn random_instance(config: &GeneratorConfig) -> (Vec<Rectangle>, Vec<Net>) {
+    let mut rng = StdRng::seed_from_u64(config.seed);
+    let n = config.num_modules;
+    if n < 2 || config.min_size < 1 || config.min_size > config.max_size {
+        panic!("an instance needs at least two modules and a size range 1 <= min_size <= max_size");
+    }
+    let modules = random_modules(&mut rng, config);
+    let grid: Vec<usize> = (0..n).collect();
+    let nets = random_nets(&mut rng, config, &grid);
     (modules, nets)
 }
 
+/// instance of the modules of a random dissection of a width x height rectangle, the sizes of the configuration are ignored
+/// returns the modules, the nets and the optimal floorplan, modules are numbered and rotated randomly,
+/// local pins are taken near the first pin of a net in the optimal floorplan
+pub fn dissected_instance(config: &GeneratorConfig, dissection: Dissection, width: Int, height: Int) -> (Vec<Rectangle>, Vec<Net>, Floorplan) {
+    let mut rng = StdRng::seed_from_u64(config.seed);
+    let n = config.num_modules;
+    if n < 2 || width * height < 4 * n {
+        panic!("a dissection needs at least two modules and an area of at least 4 per module");
+    }
+
+    // the largest part is split until there are n parts
+    let mut parts: Floorplan = vec![(0, 0, Rectangle::new(width, height))];
+    let mut first = true;
+    while parts.len() < n {
+        let largest = (0..parts.len()).max_by_key(|&i| parts[i].2.area()).unwrap();
+        let (x, y, rect) = parts.swap_remove(largest);
+        let pinwheel = dissection == Dissection::NonGuillotine() && n - parts.len() >= 5
+            && rect.width >= 3 && rect.height >= 3 && (first || rng.gen_bool(PINWHEEL_PROB));
+        first = false;
+        if pinwheel {
+            // cut positions 0 < x1 < x2 < width and 0 < y1 < y2 < height
+            let cuts = |rng: &mut StdRng, length: Int| {
+                let low = ((length as f64 * rng.gen_range(0.2..0.45)).round() as Int).clamp(1, length - 2);
+                let high = ((length as f64 * rng.gen_range(0.55..0.8)).round() as Int).clamp(low + 1, length - 1);
+                (low, high)
+            };
+            let (x1, x2) = cuts(&mut rng, rect.width);
+            let (y1, y2) = cuts(&mut rng, rect.height);
+            parts.push((x, y, Rectangle::new(x2, y1)));
+            parts.push((x + x2, y, Rectangle::new(rect.width - x2, y2)));
+            parts.push((x + x1, y + y2, Rectangle::new(rect.width - x1, rect.height - y2)));
+            parts.push((x, y + y1, Rectangle::new(x1, rect.height - y1)));
+            parts.push((x + x1, y + y1, Rectangle::new(x2 - x1, y2 - y1)));
+            continue;
+        }
+        // cut perpendicular to the longer side
+        let length = rect.width.max(rect.height);
+        let cut = ((length as f64 * rng.gen_range(0.3..0.7)).round() as Int).clamp(1, length - 1);
+        if rect.width >= rect.height {
+            parts.push((x, y, Rectangle::new(cut, rect.height)));
+            parts.push((x + cut, y, Rectangle::new(rect.width - cut, rect.height)));
+        }
+        else {
+            parts.push((x, y, Rectangle::new(rect.width, cut)));
+            parts.push((x, y + cut, Rectangle::new(rect.width, rect.height - cut)));
+        }
+    }
+    parts.shuffle(&mut rng);
+    let modules: Vec<Rectangle> = parts.iter()
+        .map(|&(_, _, rect)| if rng.gen_bool(0.5) { rect.transpose() } else { rect })
+        .collect();
+    // the virtual grid of the nets holds the modules in rows by the y- and columns by the x-coordinate of their centers
+    let side = (n as f64).sqrt().ceil() as usize;
+    let doubled_center = |i: usize| (2 * parts[i].0 + parts[i].2.width, 2 * parts[i].1 + parts[i].2.height);
+    let mut grid: Vec<usize> = (0..n).collect();
+    grid.sort_by_key(|&i| doubled_center(i).1);
+    for row in grid.chunks_mut(side) {
+        row.sort_by_key(|&i| doubled_center(i).0);
+    }
+    let nets = random_nets(&mut rng, config, &grid);
+    (modules, nets, parts)
+}
+
+fn random_modules(rng: &mut StdRng, config: &GeneratorConfig) -> Vec<Rectangle> {
+    let n = config.num_modules;
+    let mut modules: Vec<Rectangle> = (0..n)
+        .map(|_| {
+            let width = sample_length(rng, config.size_distribution, config.min_size, config.max_size);
+            let height = sample_length(rng, config.size_distribution, config.min_size, config.max_size);
+            Rectangle::new(width, height)
+        })
+        .collect();
+    let macro_size = MACRO_FACTOR * config.max_size;
+    for i in rand::seq::index::sample(rng, n, config.num_macros.min(n)) {
+        modules[i] = Rectangle::new(rng.gen_range(macro_size / 2..=macro_size), rng.gen_range(macro_size / 2..=macro_size));
+    }
+    modules
+}
+
+// grid[k] is the module in cell k of the virtual grid, cells are numbered row by row
+fn random_nets(rng: &mut StdRng, config: &GeneratorConfig, grid: &[usize]) -> Vec<Net> {
+    let n = config.num_modules;
+
+    let max_degree = config.max_degree.clamp(2, n);
+    let mut cumulative: Vec<f64> = Vec::new();
+    let mut sum = 0.0;
+    for d in 2..=max_degree {
+        sum += (d as f64).powf(-config.degree_exponent);
+        cumulative.push(sum);
+    }
+    let side = (n as f64).sqrt().ceil() as i64;
+    let mut nets: Vec<Net> = Vec::with_capacity(config.num_nets);
+    for id in 0..config.num_nets {
+        let degree = sample_degree(rng, &cumulative);
+        let first = rng.gen_range(0..n);
+        let mut pins: Vec<usize> = vec![grid[first]];
+        // the neighborhood grows with the degree such that it contains enough modules
+        let radius = (degree as f64).sqrt().ceil() as i64 + 1;
+        while pins.len() < degree {
+            let pin = if rng.gen_bool(config.locality) {
+                let x = first as i64 % side + rng.gen_range(-radius..=radius);
+                let y = first as i64 / side + rng.gen_range(-radius..=radius);
+                if x < 0 || x >= side || y < 0 || y * side + x >= n as i64 {
+                    continue;
+                }
+                grid[(y * side + x) as usize]
+            }
+            else {
+                grid[rng.gen_range(0..n)]
+            };
+            if !pins.contains(&pin) {
+                pins.push(pin);
+            }
+        }
+        nets.push(Net::new(pins, id));
+    }
+    nets
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::floorplan_common::CostFunction;
+
+    #[test]
+    fn test_random_instance() {
+        let mut config = GeneratorConfig{num_modules: 50, num_nets: 80, seed: 7, size_distribution: SizeDistribution::LogNormal(),
+            min_size: 5, max_size: 40, num_macros: 2, degree_exponent: 2.5, max_degree: 8, locality: 0.8};
+        let (modules, nets) = random_instance(&config);
+        assert_eq!((modules.len(), nets.len()), (50, 80));
+        assert_eq!(modules.iter().filter(|rect| rect.width > 40 || rect.height > 40).count(), 2);
+        for net in nets.iter() {
+            let mut pins = net.pins.clone();
+            pins.sort();
+            pins.dedup();
+            assert!(pins.len() == net.pins.len() && (2..=8).contains(&pins.len()));
+        }
+        let pins = |nets: &[Net]| nets.iter().map(|net| net.pins.clone()).collect::<Vec<Vec<usize>>>();
+        let (same_modules, same_nets) = random_instance(&config);
+        assert_eq!((same_modules, pins(&same_nets)), (modules, pins(&nets)));
+        config.seed = 8;
+        assert_ne!(pins(&random_instance(&config).1), pins(&nets));
+    }
+
+    #[test]
+    fn test_dissected_instance() {
+        let config = GeneratorConfig{num_modules: 40, num_nets: 40, seed: 3, size_distribution: SizeDistribution::Uniform(),
+            min_size: 1, max_size: 1, num_macros: 0, degree_exponent: 2.5, max_degree: 4, locality: 0.0};
+        for dissection in [Dissection::Guillotine(), Dissection::NonGuillotine()] {
+            let (modules, nets, plan) = dissected_instance(&config, dissection, 120, 80);
+            assert_eq!((modules.len(), nets.len(), plan.len()), (40, 40, 40));
+            assert_eq!(modules.iter().map(|rect| rect.area()).sum::<Int>(), 120 * 80);
+            assert!(crate::compaction::find_overlaps(&plan).is_empty());
+            assert!(plan.iter().all(|&(x, y, rect)| x + rect.width <= 120 && y + rect.height <= 80));
+            let mut pe = crate::polish_expression::PolishExpression::new(modules, nets, 1.0);
+            assert_eq!(pe.set_solution_floorplan(&plan).is_ok(), dissection == Dissection::Guillotine());
+        }
+
+        // local nets are short in the optimal floorplan
+        let mut config = GeneratorConfig{num_modules: 100, num_nets: 100, locality: 1.0, ..config};
+        let (_, local_nets, plan) = dissected_instance(&config, Dissection::NonGuillotine(), 1000, 1000);
+        config.locality = 0.0;
+        let (_, nets, same_plan) = dissected_instance(&config, Dissection::NonGuillotine(), 1000, 1000);
+        assert_eq!(plan, same_plan);
+        let local_wire = CostFunction::compute_wirelength(&plan, &local_nets);
+        let wire = CostFunction::compute_wirelength(&plan, &nets);
+        assert!(local_wire < 0.75 * wire);
+    }
+}
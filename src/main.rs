@@ -1,7 +1,8 @@
 // mod parabola;
 // mod knapsack;
-// mod genetic_algorithm;
-// mod instance_generator;
+mod genetic_algorithm;
+mod nsga2;
+mod instance_generator;
 mod simulated_annealing;
 mod polish_expression;
 mod shape_function;
@@ -11,31 +12,61 @@ mod draw;
 mod slicing_tree;
 mod floorplan_common;
 mod sequence_pair;
+mod b_star_tree;
+mod corner_block_list;
+mod transitive_closure_graph;
+mod o_tree;
+mod compaction;
+mod constraints;
+mod cost_terms;
+mod contour;
+mod crossover;
 mod hypergraph;
+mod analytical;
+mod multilevel;
+mod partitioning;
 mod time;
 
 use std::fmt::Debug;
 use crate::simulated_annealing::*;
+use crate::genetic_algorithm::*;
+use crate::nsga2::*;
 use crate::parser::*;
 use crate::draw::*;
 use crate::floorplan_common::*;
 use crate::sequence_pair::*;
+use crate::b_star_tree::*;
+use crate::corner_block_list::*;
+use crate::transitive_closure_graph::*;
+use crate::o_tree::*;
+use crate::compaction::*;
+use crate::constraints::*;
+use crate::cost_terms::*;
+use crate::crossover::CrossoverType;
 use crate::definitions::*;
 use crate::hypergraph::*;
+use crate::instance_generator::*;
+use crate::analytical::*;
+use crate::multilevel::*;
+use crate::partitioning::*;
 use crate::polish_expression::*;
-use crate::slicing_tree::ModuleShape;
+use crate::slicing_tree::{ModuleShape, Realization};
 use crate::time::*;
-use clap::Parser;
+use clap::{Parser, Subcommand};
+use rand::{rngs::StdRng, SeedableRng};
 
 /// command line arguments
 #[derive(Parser, Debug, Clone)]
 #[command(author, about, long_about = None)]
 struct Args {
+    #[command(subcommand)]
+    command: Option<Command>,
+
     /// path to .floor file
     #[arg(long, default_value_t = String::from("benchmark/n300.floor"))]
     input: String,
     
-    /// name of floorplan representation: slicing_tree, sequence_pair
+    /// name of floorplan representation: slicing_tree, sequence_pair, b_star_tree, corner_block_list, transitive_closure_graph, o_tree
     #[arg(short, long, default_value_t = String::from("sequence_pair"))]
     floorplan_type: String,
 
@@ -43,9 +74,62 @@ struct Args {
     #[arg(short, long, default_value_t = 0.5)]
     alpha: f64,
 
+    /// target aspect ratio (width / height) of the outline if aspect_ratio_weight is set
+    #[arg(long, default_value_t = 1.0)]
+    aspect_ratio: f64,
+
+    /// weight of the aspect ratio deviation in the cost function, 0 disables it
+    #[arg(long, default_value_t = 0.0)]
+    aspect_ratio_weight: f64,
+
+    /// weight of the whitespace share in the cost function, 0 disables it
+    #[arg(long, default_value_t = 0.0)]
+    whitespace_weight: f64,
+
+    /// weight of the near and apart constraints of the input file in the cost function
+    #[arg(long, default_value_t = 0.5)]
+    proximity_weight: f64,
+
+    /// weight of the penalty of violated alignment, abutment, relative order and fixed module constraints of the input file
+    #[arg(long, default_value_t = 1.0)]
+    constraint_weight: f64,
+
+    /// weight of the module area outside of the regions given in the input file
+    #[arg(long, default_value_t = 1.0)]
+    region_weight: f64,
+
+    /// optimization algorithm: simulated_annealing, genetic, memetic (genetic with SA local search),
+    /// nsga2 (Pareto front of area and wirelength, one csv line per front point)
+    #[arg(long, default_value_t = String::from("simulated_annealing"))]
+    algorithm: String,
+
     /// number of SA iterations
     #[arg(short, long, default_value_t = 10_000_000)]
     iterations: usize,
+
+    /// share of each new GA population created by mutation, the rest is created by crossover
+    #[arg(long, default_value_t = 0.05)]
+    mutation_rate: f64,
+
+    /// number of solutions in a GA population
+    #[arg(long, default_value_t = 100)]
+    population_size: usize,
+
+    /// number of GA generations
+    #[arg(long, default_value_t = 10_000)]
+    generations: usize,
+
+    /// number of best solutions copied unchanged into the next GA population
+    #[arg(long, default_value_t = 2)]
+    elite_size: usize,
+
+    /// SA iterations of the local search applied to each offspring in the memetic algorithm
+    #[arg(long, default_value_t = 1000)]
+    local_search_iterations: usize,
+
+    /// GA crossover of the sequence pair: order, pmx, cycle, random
+    #[arg(long, default_value_t = String::from("order"))]
+    crossover: String,
     
     /// use recursive bisection to get inital solution
     #[arg(short, long)]
@@ -55,6 +139,39 @@ struct Args {
     #[arg(short, long)]
     cluster_growing: bool,
 
+    /// first module of the cluster growing order: a module id or most_connected
+    #[arg(long, default_value_t = String::from("0"))]
+    start_node: String,
+
+    /// use recursive area-balanced min-cut bisection (Fiduccia-Mattheyses) to get inital solution
+    #[arg(long)]
+    min_cut: bool,
+
+    /// use force-directed global placement of the module centers to get inital solution
+    #[arg(long)]
+    analytical: bool,
+
+    /// seed of the start positions of the force-directed placement, the same seed gives the same initial solution
+    #[arg(long, default_value_t = 0)]
+    seed: u64,
+
+    /// cluster the modules, floorplan the coarsest level and refine each finer level (sequence_pair only)
+    #[arg(long)]
+    multilevel: bool,
+
+    /// number of SA iterations of each coarse level in the multilevel flow
+    #[arg(long, default_value_t = 200_000)]
+    level_iterations: usize,
+
+    /// run SA on a slicing tree first and refine its floorplan as sequence pair,
+    /// for slicing_tree the other way round if the sequence pair floorplan is slicing
+    #[arg(long)]
+    two_phase: bool,
+
+    /// number of SA iterations of the first representation in the two-phase flow
+    #[arg(long, default_value_t = 1_000_000)]
+    first_phase_iterations: usize,
+
     /// module shape type: hard, rotatable, aspect_ratios
     #[arg(long, default_value_t = String::from("rotatable"))]
     module_shape: String,
@@ -63,15 +180,128 @@ struct Args {
     #[arg(short, long, default_value_t=1)]
     min_module_length: usize,
 
+    /// slicing tree realization: min_area, min_wire (least wirelength among near-minimal area shapes)
+    #[arg(long, default_value_t = String::from("min_area"))]
+    realization: String,
+
+    /// relative area tolerance of the root shapes considered when min_wire realization is used
+    #[arg(long, default_value_t = 0.05)]
+    area_tolerance: f64,
+
+    /// check the final floorplan for overlaps and compact it to the lower left
+    #[arg(long)]
+    compact: bool,
+
+    /// spread the dead space of the final floorplan evenly between the modules
+    #[arg(long)]
+    redistribute_whitespace: bool,
+
+    /// print the gap of the area to the optimum of a generated instance as csv line instead
+    #[arg(long)]
+    benchmark: bool,
+
     /// save image of final floorplan
     #[arg(short, long)]
     save_image: bool,
 
-    /// path to output image if save_image is set
+    /// path to output image if save_image is set, nsga2 appends the index of the front point
     #[arg(short, long, default_value_t = String::from("floorplan.svg"))]
     out_image: String,
 }
 
+#[derive(Subcommand, Debug, Clone)]
+enum Command {
+    /// write a random instance to a .floor file
+    Generate(GenerateArgs),
+}
+
+/// instance generator arguments, the same arguments and seed give the same instance
+#[derive(clap::Args, Debug, Clone)]
+struct GenerateArgs {
+    /// path to the written .floor file
+    #[arg(long)]
+    output: String,
+
+    /// number of modules
+    #[arg(long, default_value_t = 100)]
+    modules: usize,
+
+    /// number of nets
+    #[arg(long, default_value_t = 100)]
+    nets: usize,
+
+    /// seed of the random number generator
+    #[arg(long, default_value_t = 0)]
+    seed: u64,
+
+    /// distribution of the module widths and heights: uniform, lognormal
+    #[arg(long, default_value_t = String::from("uniform"))]
+    sizes: String,
+
+    /// minimum module width and height
+    #[arg(long, default_value_t = 10)]
+    min_size: usize,
+
+    /// maximum module width and height
+    #[arg(long, default_value_t = 100)]
+    max_size: usize,
+
+    /// number of macros, modules up to 4 times larger than max_size in each dimension
+    #[arg(long, default_value_t = 0)]
+    macros: usize,
+
+    /// exponent of the power law of the net degrees (Rent's rule like), larger values give more 2-pin nets
+    #[arg(long, default_value_t = 2.5)]
+    degree_exponent: f64,
+
+    /// maximum number of pins of a net
+    #[arg(long, default_value_t = 10)]
+    max_degree: usize,
+
+    /// probability that a pin is taken from the neighborhood of the first pin of its net, 0 gives uniform nets
+    #[arg(long, default_value_t = 0.0)]
+    locality: f64,
+
+    /// cut a width x height rectangle into the modules instead of sampling their sizes: none, guillotine, non_guillotine
+    /// the rectangle is written to the file as optimal bounding box
+    #[arg(long, default_value_t = String::from("none"))]
+    dissection: String,
+
+    /// width of the dissected rectangle
+    #[arg(long, default_value_t = 1000)]
+    width: usize,
+
+    /// height of the dissected rectangle
+    #[arg(long, default_value_t = 1000)]
+    height: usize,
+}
+
+fn generate(args: GenerateArgs) {
+    let config = GeneratorConfig{
+        num_modules: args.modules,
+        num_nets: args.nets,
+        seed: args.seed,
+        size_distribution: SizeDistribution::from(args.sizes.clone()),
+        min_size: args.min_size,
+        max_size: args.max_size,
+        num_macros: args.macros,
+        degree_exponent: args.degree_exponent,
+        max_degree: args.max_degree,
+        locality: args.locality,
+    };
+    if args.dissection == "none" {
+        let (blocks, nets) = random_instance(&config);
+        write_file(&args.output, &blocks, &nets, None).unwrap();
+        eprintln!("--> wrote {} modules and {} nets to {}", blocks.len(), nets.len(), args.output);
+    }
+    else {
+        let dissection = Dissection::from(args.dissection.clone());
+        let (blocks, nets, _) = dissected_instance(&config, dissection, args.width, args.height);
+        write_file(&args.output, &blocks, &nets, Some(Rectangle::new(args.width, args.height))).unwrap();
+        eprintln!("--> wrote {} modules of a {} dissection of {}x{} and {} nets to {}", blocks.len(), args.dissection, args.width, args.height, nets.len(), args.output);
+    }
+}
+
 fn run_simulated_annealing<T, S, Move>(p: &mut T, config: SimulatedAnnealingConfig) 
 where 
     T: Mutation<Move> + Cost + Solution<S>,
@@ -88,10 +318,33 @@ where
         sa.run(p);    
     }
 
-fn run_algorithm<T, S, Move>(p: &mut T, sa_config: SimulatedAnnealingConfig, args: Args, instance: FloorplanInstance) 
+fn run_genetic_algorithm<T, S, Move>(p: &mut T, config: GeneticAlgorithmConfig) 
 where 
-    T: Mutation<Move> + Cost + Solution<S> + FloorCost + FloorPlan,
-    S: Clone + Debug,
+    T: Mutation<Move> + Cost + Solution<S> + RandomSolution<S> + Crossover<S>,
+    S: Clone + Debug + Default,
+    {
+        eprintln!("mutation rate: {}, population size: {}, generations: {}, elite size: {}", config.mutation_rate, config.population_size, config.generations, config.elite_size);
+        let mut ga = GeneticAlgorithm::new(config);
+        ga.run(p);
+    }
+
+fn run_optimizer<T, S, Move>(p: &mut T, sa_config: SimulatedAnnealingConfig, ga_config: GeneticAlgorithmConfig, algorithm: &str) 
+where 
+    T: Mutation<Move> + Cost + Solution<S> + RandomSolution<S> + Crossover<S>,
+    S: Clone + Debug + Default,
+    {
+        if algorithm == "genetic" || algorithm == "memetic" {
+            run_genetic_algorithm(p, ga_config);
+        }
+        else {
+            run_simulated_annealing(p, sa_config);
+        }
+    }
+
+fn run_algorithm<T, F>(p: &mut T, optimize: F, args: Args, instance: FloorplanInstance) 
+where 
+    T: FloorCost + FloorPlan,
+    F: FnOnce(&mut T),
     {
         let modules = instance.modules;
         let net_list = instance.nets;
@@ -100,26 +353,78 @@ where
         let wire_before = p.get_floor_wire();
         
         let timer = Timer::new();
-        run_simulated_annealing(p, sa_config);
+        optimize(p);
         let time_ms = timer.get_passed_ms();
 
-        let plan_after = p.get_floorplan();
-        let dead_area_after = CostFunction::get_dead_area(p, &modules);
-        let wire_after = p.get_floor_wire();
+        let mut plan_after = p.get_floorplan();
+        let mut dead_area_after = CostFunction::get_dead_area(p, &modules);
+        let mut wire_after = p.get_floor_wire();
+        let mut area_after = p.get_floor_area();
+
+        // post-processing would move fixed modules and break symmetries
+        let has_fixed_modules = !instance.constraints.get_fixed().is_empty();
+        let has_symmetry_groups = !instance.constraints.symmetry_groups.is_empty();
+        if (args.compact || args.redistribute_whitespace) && (has_fixed_modules || has_symmetry_groups) {
+            eprintln!("floorplan has fixed or symmetric modules, skipping post-processing");
+        }
+        else if args.compact || args.redistribute_whitespace {
+            let overlaps = find_overlaps(&plan_after);
+            if overlaps.is_empty() {
+                if args.compact {
+                    let report = compact(&mut plan_after).unwrap();
+                    eprintln!("compaction: area before {}, after {}, rounds {}", report.area_before, report.area_after, report.rounds);
+                }
+                if args.redistribute_whitespace {
+                    redistribute_whitespace(&mut plan_after);
+                }
+                let occupied_area: usize = modules.iter().map(|rect| rect.area()).sum();
+                area_after = get_bounding_box(&plan_after).area() as f64;
+                dead_area_after = (1.0 - occupied_area as f64 / area_after) * 100.0;
+                wire_after = CostFunction::compute_wirelength(&plan_after, &net_list);
+            }
+            else {
+                eprintln!("floorplan has {} overlapping pairs of modules, e.g. {:?}, skipping post-processing", overlaps.len(), overlaps[0]);
+            }
+        }
         let wire_reduction = (wire_after / wire_before) * 100.0;
-        let area_after = p.get_floor_area();
+        let violations = instance.constraints.find_violations(&plan_after);
     
-        eprintln!("");
+        eprintln!();
         eprintln!("dead area before {:.2?}%, after {:.2?}%", dead_area_before, dead_area_after);
         eprintln!("{:.2?}% of wirelength before", wire_reduction);
         eprintln!("total area: {}", area_after);
         eprintln!("total wire: {}", wire_after);
         eprintln!("time [s]: {:.2}", time_ms / 1000.0);
+        if !instance.constraints.placement.is_empty() {
+            eprintln!("violated constraints: {} of {}", violations.len(), instance.constraints.placement.len());
+            for c in violations.iter() {
+                eprintln!("  {:?}, distance {}", c, c.penalty(&plan_after));
+            }
+        }
+        for (g, group) in instance.constraints.symmetry_groups.iter().enumerate() {
+            let asymmetry = group.get_asymmetry(&plan_after);
+            if asymmetry > 0 {
+                eprintln!("symmetry group {} is not symmetric, deviation {}", g, asymmetry);
+            }
+        }
         
         let svg_image = &args.out_image;
         let draw_nets = false;
         if args.save_image {
-            draw_floorplan(&plan_after, svg_image, &net_list, draw_nets);
+            draw_floorplan(&plan_after, svg_image, &net_list, draw_nets, &instance.constraints.get_regions());
+        }
+
+        // the genetic algorithms run generations instead of iterations
+        let iterations = if args.algorithm == "genetic" || args.algorithm == "memetic" { args.generations } else { args.iterations };
+        if let Some(optimum) = instance.optimum {
+            let gap = (area_after / optimum.area() as f64 - 1.0) * 100.0;
+            eprintln!("optimal area: {}, gap {:.2}%", optimum.area(), gap);
+            if args.benchmark {
+                // header
+                // instance,floorplan,alpha,time[ms],total_area,optimal_area,area_gap[%],total_wire,iterations
+                println!("{},{},{},{:.2},{},{},{:.2},{},{}", args.input, args.floorplan_type, args.alpha, time_ms, area_after, optimum.area(), gap, wire_after, iterations);
+                return;
+            }
         }
 
         // output for csv
@@ -132,21 +437,186 @@ where
         print!("{},", area_after);
         print!("{:.2},", dead_area_after);
         print!("{},", wire_after);
-        print!("{},", args.iterations);
+        print!("{},", iterations);
         print!("{},", args.cluster_growing);
         print!("{}", args.recursive_bisection);
-        println!("")
+        println!()
     }
 
+fn run_multi_objective<T, S, Move>(p: &mut T, config: Nsga2Config, args: Args, instance: FloorplanInstance) 
+where 
+    T: Mutation<Move> + Solution<S> + RandomSolution<S> + Crossover<S> + FloorCost + FloorPlan,
+    S: Clone,
+    {
+        let modules = instance.modules;
+        let net_list = instance.nets;
+
+        eprintln!("mutation rate: {}, population size: {}, generations: {}", config.mutation_rate, config.population_size, config.generations);
+        let timer = Timer::new();
+        let mut nsga2 = Nsga2::new(config);
+        let front = nsga2.run(p);
+        let time_ms = timer.get_passed_ms();
+
+        eprintln!();
+        eprintln!("{} non-dominated floorplans", front.len());
+        eprintln!("time [s]: {:.2}", time_ms / 1000.0);
+        if let Some(optimum) = instance.optimum {
+            let min_area = front.iter().map(|(_, (area, _))| *area).fold(f64::MAX, f64::min);
+            eprintln!("optimal area: {}, gap of the smallest area {:.2}%", optimum.area(), (min_area / optimum.area() as f64 - 1.0) * 100.0);
+        }
+
+        // output for csv, one line per front point
+        // header
+        // instance,floorplan,point,time[ms],total_area,dead_area,total_wire,generations
+        // with --benchmark and a known optimum
+        // instance,floorplan,point,time[ms],total_area,optimal_area,area_gap[%],total_wire,generations
+        let stem = args.out_image.strip_suffix(".svg").unwrap_or(&args.out_image);
+        for (i, (solution, (area, wire))) in front.into_iter().enumerate() {
+            p.set_solution(solution);
+            let dead_area = CostFunction::get_dead_area(p, &modules);
+            if args.save_image {
+                let draw_nets = false;
+                draw_floorplan(&p.get_floorplan(), &format!("{}_{}.svg", stem, i), &net_list, draw_nets, &instance.constraints.get_regions());
+            }
+            match instance.optimum.filter(|_| args.benchmark) {
+                Some(optimum) => {
+                    let gap = (area / optimum.area() as f64 - 1.0) * 100.0;
+                    println!("{},{},{},{:.2},{},{},{:.2},{},{}", args.input, args.floorplan_type, i, time_ms, area, optimum.area(), gap, wire, args.generations);
+                }
+                None => println!("{},{},{},{:.2},{},{:.2},{},{}", args.input, args.floorplan_type, i, time_ms, area, dead_area, wire, args.generations),
+            }
+        }
+    }
+
+fn run_representation<T, S, Move>(p: &mut T, sa_config: SimulatedAnnealingConfig, ga_config: GeneticAlgorithmConfig, nsga2_config: Nsga2Config, args: Args, instance: FloorplanInstance) 
+where 
+    T: Mutation<Move> + Cost + Solution<S> + RandomSolution<S> + Crossover<S> + FloorCost + FloorPlan,
+    S: Clone + Debug + Default,
+    {
+        if args.algorithm == "nsga2" {
+            run_multi_objective(p, nsga2_config, args, instance);
+        }
+        else {
+            let algorithm = args.algorithm.clone();
+            run_algorithm(p, |p| run_optimizer(p, sa_config, ga_config, &algorithm), args, instance);
+        }
+    }
+
+fn new_polish_expression(args: &Args, blocks: Vec<Rectangle>, nets: Vec<Net>) -> PolishExpression {
+    let mut p: PolishExpression = PolishExpression::new(blocks, nets, args.alpha);
+    let mut module_shape: ModuleShape = ModuleShape::from(args.module_shape.clone());
+    module_shape.set_min_module_length(args.min_module_length);
+    p.set_module_shape(module_shape);
+    let mut realization: Realization = Realization::from(args.realization.clone());
+    realization.set_area_tolerance(args.area_tolerance);
+    p.set_realization(realization);
+    p
+}
+
+// additional cost terms with non-zero weight
+fn build_cost_terms(args: &Args, modules: &[Rectangle], constraints: &Constraints) -> Vec<WeightedCostTerm> {
+    let mut terms: Vec<WeightedCostTerm> = Vec::new();
+    if args.aspect_ratio_weight > 0.0 {
+        let term = AspectRatioTerm{target: args.aspect_ratio};
+        terms.push(WeightedCostTerm::new(Box::new(term), args.aspect_ratio_weight));
+    }
+    if args.whitespace_weight > 0.0 {
+        let term = WhitespaceTerm::new(modules);
+        terms.push(WeightedCostTerm::new(Box::new(term), args.whitespace_weight));
+    }
+    if args.proximity_weight > 0.0 && !constraints.near.is_empty() {
+        let term = NearTerm{pairs: constraints.near.clone()};
+        terms.push(WeightedCostTerm::new(Box::new(term), args.proximity_weight));
+    }
+    if args.proximity_weight > 0.0 && !constraints.apart.is_empty() {
+        let term = ApartTerm{pairs: constraints.apart.clone()};
+        terms.push(WeightedCostTerm::new(Box::new(term), args.proximity_weight));
+    }
+    let placement: Vec<PlacementConstraint> = constraints.placement.iter()
+        .filter(|c| !matches!(c, PlacementConstraint::Region(..)))
+        .copied()
+        .collect();
+    if args.constraint_weight > 0.0 && !placement.is_empty() {
+        let term = PlacementConstraintTerm{constraints: placement};
+        terms.push(WeightedCostTerm::new(Box::new(term), args.constraint_weight));
+    }
+    let regions: Vec<PlacementConstraint> = constraints.get_regions().into_iter()
+        .map(|(a, x, y, region)| PlacementConstraint::Region(a, x, y, region))
+        .collect();
+    if args.region_weight > 0.0 && !regions.is_empty() {
+        let term = PlacementConstraintTerm{constraints: regions};
+        terms.push(WeightedCostTerm::new(Box::new(term), args.region_weight));
+    }
+    terms
+}
+
 fn cli() {
     let args = Args::parse();
+    if let Some(Command::Generate(generate_args)) = args.command {
+        generate(generate_args);
+        return;
+    }
     eprintln!("{:?} \n", args);
 
     eprintln!("--> reading file: {}", args.input);
     let (blocks, nets) = parse_file(args.input.clone()).unwrap();
+    let constraints = parse_constraints(args.input.clone()).unwrap();
+    let optimum = parse_optimum(args.input.clone()).unwrap();
+    if args.benchmark && optimum.is_none() {
+        panic!("benchmark needs an instance with known optimum, see the generate command");
+    }
     eprintln!("modules: {}, nets: {}, alpha {}", blocks.len(), nets.len(), args.alpha);
+    if !constraints.is_empty() {
+        eprintln!("constraints: {} near, {} apart, {} placement, {} symmetry groups", constraints.near.len(), constraints.apart.len(), constraints.placement.len(), constraints.symmetry_groups.len());
+    }
     eprintln!("using {} floorplan representation", args.floorplan_type.clone());
-    if args.recursive_bisection {
+    let is_genetic = args.algorithm == "genetic" || args.algorithm == "memetic" || args.algorithm == "nsga2";
+    if args.algorithm != "simulated_annealing" && !is_genetic {
+        panic!("unknown algorithm {}", args.algorithm);
+    }
+    if is_genetic && args.population_size < 2 {
+        panic!("population size must be at least 2 for crossover");
+    }
+    eprintln!("using {} algorithm", args.algorithm);
+    if is_genetic && args.floorplan_type == "sequence_pair" {
+        eprintln!("using {} crossover", args.crossover);
+    }
+    if !constraints.symmetry_groups.is_empty() && args.floorplan_type != "sequence_pair" {
+        eprintln!("symmetry constraints are only supported by sequence_pair, ignoring them");
+    }
+    if args.multilevel && args.floorplan_type != "sequence_pair" {
+        eprintln!("multilevel floorplanning is only supported by sequence_pair, ignoring it");
+    }
+    else if args.multilevel {
+        eprintln!("using multilevel floorplanning, {} iterations per coarse level", args.level_iterations);
+    }
+    if args.two_phase && args.floorplan_type != "sequence_pair" && args.floorplan_type != "slicing_tree" {
+        eprintln!("the two-phase flow is only supported by sequence_pair and slicing_tree, ignoring it");
+    }
+    else if args.two_phase && args.multilevel && args.floorplan_type == "sequence_pair" {
+        eprintln!("the two-phase flow can not be combined with multilevel floorplanning, ignoring it");
+    }
+    else if args.two_phase {
+        let first = if args.floorplan_type == "sequence_pair" { "slicing tree" } else { "sequence pair" };
+        eprintln!("using two-phase flow, {} iterations on the {}", args.first_phase_iterations, first);
+    }
+    let supports_recursive_bisection = args.floorplan_type == "slicing_tree" || args.floorplan_type == "sequence_pair";
+    if (args.recursive_bisection || args.min_cut) && !supports_recursive_bisection {
+        eprintln!("recursive bisection is not supported for {}, ignoring it", args.floorplan_type);
+    }
+    if args.analytical && !supports_recursive_bisection {
+        eprintln!("analytical placement is not supported for {}, ignoring it", args.floorplan_type);
+    }
+    else if args.analytical {
+        if args.min_cut || args.recursive_bisection {
+            eprintln!("analytical placement can not be combined with recursive bisection, ignoring the bisection");
+        }
+        eprintln!("using force-directed global placement, seed {}", args.seed);
+    }
+    else if args.min_cut {
+        eprintln!("using recursive min-cut bisection");
+    }
+    else if args.recursive_bisection {
         if args.cluster_growing {
             eprintln!("using recursive bisection with cluster growing");
         }
@@ -159,41 +629,158 @@ fn cli() {
         if args.module_shape == "aspect_ratios" {
             eprintln!("using minimum module length {}", args.min_module_length);
         }
+        eprintln!("using {} realization", args.realization);
+        if args.realization == "min_wire" {
+            eprintln!("using area tolerance {}", args.area_tolerance);
+        }
     }
-    eprintln!("");
+    eprintln!();
 
-    let graph = Hypergraph::from(nets.clone());
+    let graph = Hypergraph::new(blocks.len(), &nets);
     let mut order: Vec<Int> = (0..graph.num_nodes).collect();
 
     if args.cluster_growing {
-        order = cluster_growing_order(&graph, 0);
+        let start_node = if args.start_node == "most_connected" {
+            most_connected_node(&graph)
+        }
+        else {
+            args.start_node.parse::<Int>().ok().filter(|&v| v < graph.num_nodes).unwrap_or_else(|| panic!("unknown start node {}", args.start_node))
+        };
+        let (cluster_order, duration) = measure(|| cluster_growing_order(&graph, start_node, &blocks));
+        order = cluster_order;
+        eprintln!("cluster growing order from module {} in {:.3} ms", start_node, duration.as_secs_f64() * 1000.0);
+    }
+    let mut cuts: Vec<usize> = area_balanced_cuts(&order, &blocks);
+    if args.min_cut && !args.analytical {
+        (order, cuts) = min_cut_bisection(&graph, &blocks);
+        // instances with at most two modules are not partitioned and keep their order
+        if let Some(&cut) = cuts.first() {
+            let mut first_part: Vec<bool> = vec![false; order.len()];
+            for &v in order[..cut].iter() {
+                first_part[v] = true;
+            }
+            eprintln!("min-cut bisection: {} nets cut by the first bisection", get_cut_size(&graph, &first_part));
+        }
+    }
+    let mut positions: Vec<(f64, f64)> = Vec::new();
+    if args.analytical {
+        let (placement, duration) = measure(|| force_directed_placement(&graph, &blocks, &mut StdRng::seed_from_u64(args.seed)));
+        positions = placement;
+        (order, cuts) = slicing_order_from_positions(&positions, &blocks);
+        eprintln!("force-directed placement in {:.3} ms", duration.as_secs_f64() * 1000.0);
     }
     
     let modules = blocks.clone();
     let net_list = nets.clone();
-    let instance = FloorplanInstance{modules: modules, nets: net_list};
+    let instance = FloorplanInstance{modules, nets: net_list, constraints: constraints.clone(), optimum};
 
     let iterations = args.iterations;
     let num_moves_estimation = 3 * blocks.len();
-    let inital_prob =  0.95;
+    // the projected solution of the multilevel flow is only refined
+    let multilevel = args.multilevel && args.floorplan_type == "sequence_pair";
+    let two_phase = args.two_phase && (args.floorplan_type == "sequence_pair" || args.floorplan_type == "slicing_tree") && !multilevel;
+    let inital_prob = if multilevel || two_phase { REFINEMENT_PROB } else { 0.95 };
     let sa_config = SimulatedAnnealingConfig{iterations, num_moves_estimation, inital_prob};
 
+    let mutation_rate = args.mutation_rate;
+    let population_size = args.population_size;
+    let generations = args.generations;
+    let elite_size = args.elite_size;
+    // the plain genetic algorithm has no local search
+    let local_search_iterations = if args.algorithm == "memetic" { args.local_search_iterations } else { 0 };
+    let ga_config = GeneticAlgorithmConfig{mutation_rate, population_size, generations, elite_size, local_search_iterations};
+    let nsga2_config = Nsga2Config{mutation_rate, population_size, generations};
+
+    let cost_terms = build_cost_terms(&args, &blocks, &constraints);
+
     if args.floorplan_type == "slicing_tree" {
-        let mut p: PolishExpression = PolishExpression::new(blocks, nets, args.alpha);
-        let mut module_shape: ModuleShape = ModuleShape::from(args.module_shape.clone());
-        module_shape.set_min_module_length(args.min_module_length);
-        p.set_module_shape(module_shape);
-        if args.recursive_bisection {
-            p.set_solution_recursive_bisection(&order);
+        let mut p = new_polish_expression(&args, blocks.clone(), nets.clone());
+        p.set_cost_terms(cost_terms);
+        let mut from_sequence_pair = false;
+        if two_phase {
+            // the sequence pair floorplan is the start of the slicing tree if it is slicing
+            let mut sp = SequencePair::new(blocks.clone(), nets, args.alpha);
+            sp.set_cost_terms(build_cost_terms(&args, &blocks, &constraints));
+            if args.analytical {
+                sp.set_solution_positions(&positions);
+            }
+            else if args.recursive_bisection || args.min_cut {
+                sp.set_solution_recursive_bisection(&order, &cuts);
+            }
+            let config = SimulatedAnnealingConfig{iterations: args.first_phase_iterations, num_moves_estimation, inital_prob: 0.95};
+            run_simulated_annealing(&mut sp, config);
+            eprintln!("sequence pair phase: area {}, wire {}", sp.get_floor_area(), sp.get_floor_wire());
+            match p.set_solution_floorplan(&sp.get_floorplan()) {
+                Ok(()) => from_sequence_pair = true,
+                Err(part) => eprintln!("the sequence pair floorplan is not slicing, no guillotine cut through {} modules, using the initial slicing tree", part.len()),
+            }
+        }
+        if !from_sequence_pair && (args.recursive_bisection || args.min_cut || args.analytical) {
+            p.set_solution_recursive_bisection(&order, &cuts);
         } 
-        run_algorithm(&mut p, sa_config, args, instance);
+        // only a converted floorplan is refined
+        let sa_config = if two_phase && !from_sequence_pair { SimulatedAnnealingConfig{inital_prob: 0.95, ..sa_config} } else { sa_config };
+        run_representation(&mut p, sa_config, ga_config, nsga2_config, args, instance);
     }
     else if args.floorplan_type == "sequence_pair" {
-        let mut p = SequencePair::new(blocks, nets, args.alpha);
-        if args.recursive_bisection {
-            p.set_solution_recursive_bisection(&order);
+        // fixed modules are set before any initial solution, so they keep their orientation
+        let mut p = if multilevel {
+            let config = MultilevelConfig{alpha: args.alpha, iterations: args.level_iterations, num_moves_estimation};
+            multilevel_sequence_pair(blocks, nets, &constraints.get_fixed(), config)
+        }
+        else if two_phase {
+            // the slicing floorplan is the start of the sequence pair
+            let mut pe = new_polish_expression(&args, blocks.clone(), nets.clone());
+            pe.set_cost_terms(build_cost_terms(&args, &blocks, &constraints));
+            if args.recursive_bisection || args.min_cut || args.analytical {
+                pe.set_solution_recursive_bisection(&order, &cuts);
+            }
+            let config = SimulatedAnnealingConfig{iterations: args.first_phase_iterations, num_moves_estimation, inital_prob: 0.95};
+            run_simulated_annealing(&mut pe, config);
+            eprintln!("slicing phase: area {}, wire {}", pe.get_floor_area(), pe.get_floor_wire());
+            let mut sp = SequencePair::new(blocks, nets, args.alpha);
+            sp.set_fixed_modules(&constraints.get_fixed());
+            sp.set_solution_floorplan(&pe.get_floorplan());
+            sp
+        }
+        else {
+            let mut sp = SequencePair::new(blocks, nets, args.alpha);
+            sp.set_fixed_modules(&constraints.get_fixed());
+            sp
+        };
+        p.set_crossover_type(CrossoverType::from(args.crossover.clone()));
+        p.set_symmetry_groups(&constraints.symmetry_groups);
+        p.set_cost_terms(cost_terms);
+        if args.analytical && !multilevel && !two_phase {
+            p.set_solution_positions(&positions);
+        }
+        else if (args.recursive_bisection || args.min_cut) && !multilevel && !two_phase {
+            p.set_solution_recursive_bisection(&order, &cuts);
         } 
-        run_algorithm(&mut p, sa_config, args, instance);
+        run_representation(&mut p, sa_config, ga_config, nsga2_config, args, instance);
+        if p.get_asymmetric_packings() > 0 {
+            eprintln!("{} packings left a symmetry group asymmetric", p.get_asymmetric_packings());
+        }
+    }
+    else if args.floorplan_type == "b_star_tree" {
+        let mut p = BStarTree::new(blocks, nets, args.alpha);
+        p.set_cost_terms(cost_terms);
+        run_representation(&mut p, sa_config, ga_config, nsga2_config, args, instance);
+    }
+    else if args.floorplan_type == "corner_block_list" {
+        let mut p = CornerBlockList::new(blocks, nets, args.alpha);
+        p.set_cost_terms(cost_terms);
+        run_representation(&mut p, sa_config, ga_config, nsga2_config, args, instance);
+    }
+    else if args.floorplan_type == "transitive_closure_graph" {
+        let mut p = TransitiveClosureGraph::new(blocks, nets, args.alpha);
+        p.set_cost_terms(cost_terms);
+        run_representation(&mut p, sa_config, ga_config, nsga2_config, args, instance);
+    }
+    else if args.floorplan_type == "o_tree" {
+        let mut p = OTree::new(blocks, nets, args.alpha);
+        p.set_cost_terms(cost_terms);
+        run_representation(&mut p, sa_config, ga_config, nsga2_config, args, instance);
     }
     else {
         panic!("unknown floorplan type {}", args.floorplan_type);
@@ -0,0 +1,193 @@
+use crate::definitions::*;
+use crate::floorplan_common::*;
+use crate::hypergraph::Hypergraph;
+use crate::sequence_pair::*;
+use crate::simulated_annealing::SimulatedAnnealing;
+use rand::prelude::*;
+
+// coarsening stops at this number of clusters
+const COARSEST_SIZE: usize = 30;
+// or if a level keeps more than this share of the clusters
+const MIN_REDUCTION: f64 = 0.9;
+// a cluster is at most this multiple of the average module area
+const MAX_CLUSTER_AREA_FACTOR: usize = 12;
+// probability that a worse move is accepted at the start of the refinement of a projected level
+pub const REFINEMENT_PROB: f64 = 0.3;
+
+pub struct MultilevelConfig {
+    pub alpha: f64,
+    pub iterations: usize, // SA iterations of each coarse level
+    pub num_moves_estimation: usize,
+}
+
+/// modules and nets of a level, members of each cluster in the next finer level
+struct Level {
+    modules: Vec<Rectangle>,
+    nets: Vec<Net>,
+    members: Vec<Vec<usize>>,
+}
+
+// soft clusters are square until they are floorplanned
+fn get_cluster_shape(area: Int) -> Rectangle {
+    let width = ((area as f64).sqrt().ceil() as Int).max(1);
+    Rectangle::new(width, area.div_ceil(width))
+}
+
+/// heavy edge matching: each node is merged with the unclustered neighbor of highest connectivity,
+/// the connectivity of u and v is the sum of 1 / (|e| - 1) over their common nets e
+fn cluster(modules: &[Rectangle], nets: &[Net], max_area: Int) -> Vec<Vec<usize>> {
+    let n = modules.len();
+    let graph = Hypergraph::new(n, nets);
+    let mut order: Vec<usize> = (0..n).collect();
+    order.shuffle(&mut rand::thread_rng());
+
+    let mut clustered: Vec<bool> = vec![false; n];
+    let mut rating: Vec<f64> = vec![0.0; n];
+    let mut neighbors: Vec<usize> = Vec::new();
+    let mut clusters: Vec<Vec<usize>> = Vec::new();
+    for v in order {
+        if clustered[v] {
+            continue;
+        }
+        for &e in graph.nets(v).iter() {
+            let pins = graph.pins(e);
+            for &u in pins.iter() {
+                if !clustered[u] && u != v && modules[u].area() + modules[v].area() <= max_area {
+                    if rating[u] == 0.0 {
+                        neighbors.push(u);
+                    }
+                    rating[u] += 1.0 / (pins.len() - 1) as f64;
+                }
+            }
+        }
+        let best = neighbors.iter().copied().max_by(|&a, &b| rating[a].total_cmp(&rating[b]));
+        for u in neighbors.drain(..) {
+            rating[u] = 0.0;
+        }
+        clustered[v] = true;
+        match best {
+            Some(u) => {
+                clustered[u] = true;
+                clusters.push(vec![v, u]);
+            }
+            None => clusters.push(vec![v]),
+        }
+    }
+    clusters
+}
+
+fn coarsen(modules: &[Rectangle], nets: &[Net], max_area: Int) -> Level {
+    let members = cluster(modules, nets, max_area);
+    let mut cluster_of: Vec<usize> = vec![0; modules.len()];
+    for (c, m) in members.iter().enumerate() {
+        for &i in m.iter() {
+            cluster_of[i] = c;
+        }
+    }
+    let coarse_modules: Vec<Rectangle> = members.iter()
+        .map(|m| get_cluster_shape(m.iter().map(|&i| modules[i].area()).sum()))
+        .collect();
+
+    // nets inside a cluster are dropped
+    let mut coarse_nets: Vec<Net> = Vec::new();
+    for net in nets.iter() {
+        let mut pins: Vec<usize> = net.pins.iter().map(|&i| cluster_of[i]).collect();
+        pins.sort_unstable();
+        pins.dedup();
+        if pins.len() > 1 {
+            coarse_nets.push(Net::new(pins, coarse_nets.len()));
+        }
+    }
+    Level{modules: coarse_modules, nets: coarse_nets, members}
+}
+
+// replaces each cluster by its members, they are placed side by side along the longer side of the cluster
+fn project(solution: &SequencePairSolution, members: &[Vec<usize>], modules: &[Rectangle]) -> SequencePairSolution {
+    let (x_sequence, y_sequence, clusters) = solution;
+    let horizontal = |c: usize| clusters[c].width >= clusters[c].height;
+    let x_sequence: Vec<Int> = x_sequence.iter()
+        .flat_map(|&c| {
+            let m = members[c].iter().copied();
+            // a below b: a after b in the x sequence
+            if horizontal(c) { m.collect::<Vec<Int>>() } else { m.rev().collect() }
+        })
+        .collect();
+    let y_sequence: Vec<Int> = y_sequence.iter().flat_map(|&c| members[c].iter().copied()).collect();
+    (x_sequence, y_sequence, modules.to_vec())
+}
+
+fn refine(sp: &mut SequencePair, initial_prob: f64, config: &MultilevelConfig) {
+    let temperature = SimulatedAnnealing::estimate_initial_temperature(initial_prob, config.num_moves_estimation, sp);
+    let decay = SimulatedAnnealing::get_decay_for_n_iterations(config.iterations, temperature);
+    let mut sa = SimulatedAnnealing::new(config.iterations, temperature, decay);
+    sa.set_verbose(false);
+    sa.run(sp);
+}
+
+/// multilevel floorplanning: the modules are clustered until few clusters are left,
+/// the coarsest level is floorplanned and each finer level is refined starting from the projection of the coarser one,
+/// the returned sequence pair of the original modules is seeded but not yet refined,
+/// fixed modules are pinned in the original level before its solution is set
+pub fn multilevel_sequence_pair(modules: Vec<Rectangle>, nets: Vec<Net>, fixed: &[(usize, Int, Int)], config: MultilevelConfig) -> SequencePair {
+    let n = modules.len();
+    let max_area = MAX_CLUSTER_AREA_FACTOR * modules.iter().map(|rect| rect.area()).sum::<Int>() / n.max(1);
+    let mut levels: Vec<Level> = vec![Level{modules, nets, members: Vec::new()}];
+    loop {
+        let finer = levels.last().unwrap();
+        if finer.modules.len() <= COARSEST_SIZE {
+            break;
+        }
+        let coarser = coarsen(&finer.modules, &finer.nets, max_area);
+        if coarser.modules.len() as f64 > MIN_REDUCTION * finer.modules.len() as f64 {
+            break;
+        }
+        levels.push(coarser);
+    }
+    let sizes: Vec<usize> = levels.iter().map(|level| level.modules.len()).collect();
+    eprintln!("multilevel: {} levels with {:?} modules", levels.len(), sizes);
+
+    let coarsest = levels.pop().unwrap();
+    let mut sp = SequencePair::new(coarsest.modules, coarsest.nets, config.alpha);
+    let mut members = coarsest.members;
+    if !levels.is_empty() {
+        refine(&mut sp, 0.95, &config);
+    }
+    else {
+        sp.set_fixed_modules(fixed);
+    }
+    while let Some(level) = levels.pop() {
+        let size = level.modules.len();
+        let solution = project(&sp.copy_solution(), &members, &level.modules);
+        sp = SequencePair::new(level.modules, level.nets, config.alpha);
+        if levels.is_empty() {
+            sp.set_fixed_modules(fixed);
+        }
+        sp.set_solution(solution);
+        sp.update_cost_function();
+        if !levels.is_empty() {
+            refine(&mut sp, REFINEMENT_PROB, &config);
+        }
+        eprintln!("level with {} modules: area {}, wire {}", size, sp.get_floor_area(), sp.get_floor_wire());
+        members = level.members;
+    }
+    sp
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_coarsen() {
+        let modules: Vec<Rectangle> = vec![Rectangle::new(2, 2); 5];
+        let nets: Vec<Net> = vec![Net::new(vec![0, 1], 0), Net::new(vec![1, 2, 3], 1), Net::new(vec![0, 1, 4], 2)];
+        let level = coarsen(&modules, &nets, 8);
+        let mut covered: Vec<usize> = level.members.iter().flatten().copied().collect();
+        covered.sort();
+        assert_eq!(covered, vec![0, 1, 2, 3, 4]);
+        assert!(level.members.iter().all(|m| m.len() <= 2));
+        assert!(level.modules.len() < 5);
+        assert!(level.nets.iter().all(|net| net.pins.len() > 1 && net.pins.iter().all(|&c| c < level.modules.len())));
+        assert_eq!(get_cluster_shape(8), Rectangle::new(3, 3));
+    }
+}
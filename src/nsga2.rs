@@ -0,0 +1,239 @@
+use std::marker::PhantomData;
+
+use crate::floorplan_common::*;
+use rand::prelude::*;
+
+// area and wirelength of a solution, both are minimized
+pub type Objectives = (f64, f64);
+
+pub struct Nsga2Config {
+    pub mutation_rate: f64,
+    pub population_size: usize,
+    pub generations: usize,
+}
+
+/// a dominates b if it is not worse in both objectives and better in one
+pub fn dominates(a: Objectives, b: Objectives) -> bool {
+    a.0 <= b.0 && a.1 <= b.1 && (a.0 < b.0 || a.1 < b.1)
+}
+
+/// fast non-dominated sorting, returns the indices of each front starting with the non-dominated solutions
+pub fn non_dominated_sort(objectives: &[Objectives]) -> Vec<Vec<usize>> {
+    let n = objectives.len();
+    let mut dominated: Vec<Vec<usize>> = vec![Vec::new(); n]; // solutions dominated by i
+    let mut num_dominating: Vec<usize> = vec![0; n];         // number of solutions dominating i
+    for i in 0..n {
+        for j in i + 1..n {
+            if dominates(objectives[i], objectives[j]) {
+                dominated[i].push(j);
+                num_dominating[j] += 1;
+            }
+            else if dominates(objectives[j], objectives[i]) {
+                dominated[j].push(i);
+                num_dominating[i] += 1;
+            }
+        }
+    }
+    let mut fronts: Vec<Vec<usize>> = Vec::new();
+    let mut front: Vec<usize> = (0..n).filter(|&i| num_dominating[i] == 0).collect();
+    while !front.is_empty() {
+        let mut next_front: Vec<usize> = Vec::new();
+        for &i in front.iter() {
+            for &j in dominated[i].iter() {
+                num_dominating[j] -= 1;
+                if num_dominating[j] == 0 {
+                    next_front.push(j);
+                }
+            }
+        }
+        fronts.push(front);
+        front = next_front;
+    }
+    fronts
+}
+
+/// crowding distance of each solution in the front, boundary solutions get an infinite distance
+pub fn crowding_distance(objectives: &[Objectives], front: &[usize]) -> Vec<f64> {
+    let m = front.len();
+    let mut distance = vec![0.0; m];
+    let getters: [fn(&Objectives) -> f64; 2] = [|o| o.0, |o| o.1];
+    for get in getters.iter() {
+        let mut order: Vec<usize> = (0..m).collect();
+        order.sort_by(|&a, &b| get(&objectives[front[a]]).total_cmp(&get(&objectives[front[b]])));
+        let min = get(&objectives[front[order[0]]]);
+        let max = get(&objectives[front[order[m - 1]]]);
+        distance[order[0]] = f64::INFINITY;
+        distance[order[m - 1]] = f64::INFINITY;
+        if max > min {
+            for k in 1..m.saturating_sub(1) {
+                let prev = get(&objectives[front[order[k - 1]]]);
+                let next = get(&objectives[front[order[k + 1]]]);
+                distance[order[k]] += (next - prev) / (max - min);
+            }
+        }
+    }
+    distance
+}
+
+/// NSGA-II (Deb et al.) minimizing area and wirelength at the same time
+pub struct Nsga2<T, S, Move> {
+    config: Nsga2Config,
+    population: Vec<S>,
+    objectives: Vec<Objectives>,
+    rank: Vec<usize>,
+    crowding: Vec<f64>,
+    rng: ThreadRng,
+    phantom1: PhantomData<T>,
+    phantom2: PhantomData<Move>,
+}
+
+impl<T, S, Move> Nsga2<T, S, Move>
+where
+    T: Solution<S> + RandomSolution<S> + Mutation<Move> + Crossover<S> + FloorCost,
+    S: Clone
+{
+    pub fn new(config: Nsga2Config) -> Self {
+        Nsga2 {
+            config,
+            population: Vec::new(),
+            objectives: Vec::new(),
+            rank: Vec::new(),
+            crowding: Vec::new(),
+            rng: rand::thread_rng(),
+            phantom1: PhantomData,
+            phantom2: PhantomData,
+        }
+    }
+
+    fn evaluate(instance: &T) -> Objectives {
+        (instance.get_floor_area(), instance.get_floor_wire())
+    }
+
+    // the current solution of the instance is kept as first member
+    fn initialize_population(&mut self, instance: &mut T) {
+        self.population = vec![instance.copy_solution()];
+        self.objectives = vec![Self::evaluate(instance)];
+        for _ in 1..self.config.population_size {
+            let solution = instance.random_solution();
+            instance.set_solution(solution.clone());
+            self.population.push(solution);
+            self.objectives.push(Self::evaluate(instance));
+        }
+        self.compute_rank_and_crowding();
+    }
+
+    fn compute_rank_and_crowding(&mut self) {
+        let n = self.population.len();
+        self.rank = vec![0; n];
+        self.crowding = vec![0.0; n];
+        for (r, front) in non_dominated_sort(&self.objectives).iter().enumerate() {
+            let distance = crowding_distance(&self.objectives, front);
+            for (k, &i) in front.iter().enumerate() {
+                self.rank[i] = r;
+                self.crowding[i] = distance[k];
+            }
+        }
+    }
+
+    // binary tournament on rank and crowding distance
+    fn select(&mut self) -> usize {
+        let n = self.population.len();
+        let a = self.rng.gen_range(0..n);
+        let b = self.rng.gen_range(0..n);
+        if self.rank[a] < self.rank[b] || (self.rank[a] == self.rank[b] && self.crowding[a] >= self.crowding[b]) {
+            a
+        }
+        else {
+            b
+        }
+    }
+
+    fn create_offspring(&mut self, instance: &mut T) -> (Vec<S>, Vec<Objectives>) {
+        let mut offspring: Vec<S> = Vec::with_capacity(self.config.population_size);
+        let mut objectives: Vec<Objectives> = Vec::with_capacity(self.config.population_size);
+        for _ in 0..self.config.population_size {
+            if self.rng.gen_bool(self.config.mutation_rate) {
+                let i = self.select();
+                instance.set_solution(self.population[i].clone());
+                let _move = instance.get_random_move();
+                instance.apply_move(&_move, true);
+                offspring.push(instance.copy_solution());
+            }
+            else {
+                let i = self.select();
+                let j = self.select();
+                let solution = instance.crossover(&self.population[i], &self.population[j]);
+                instance.set_solution(solution.clone());
+                offspring.push(solution);
+            }
+            objectives.push(Self::evaluate(instance));
+        }
+        (offspring, objectives)
+    }
+
+    // keeps the best fronts of parents and offspring, the last front is truncated by crowding distance
+    fn select_next_population(&mut self, offspring: Vec<S>, offspring_objectives: Vec<Objectives>) {
+        let mut combined: Vec<Option<S>> = self.population.drain(..).chain(offspring).map(Some).collect();
+        let mut combined_objectives = std::mem::take(&mut self.objectives);
+        combined_objectives.extend(offspring_objectives);
+
+        for front in non_dominated_sort(&combined_objectives).iter() {
+            let free = self.config.population_size - self.population.len();
+            if free == 0 {
+                break;
+            }
+            let mut selected = front.clone();
+            if selected.len() > free {
+                let distance = crowding_distance(&combined_objectives, front);
+                let mut order: Vec<usize> = (0..front.len()).collect();
+                order.sort_by(|&a, &b| distance[b].total_cmp(&distance[a]));
+                selected = order[..free].iter().map(|&k| front[k]).collect();
+            }
+            for i in selected {
+                self.population.push(combined[i].take().unwrap());
+                self.objectives.push(combined_objectives[i]);
+            }
+        }
+        self.compute_rank_and_crowding();
+    }
+
+    /// returns the non-dominated solutions of the final population sorted by area
+    pub fn run(&mut self, instance: &mut T) -> Vec<(S, Objectives)> {
+        self.initialize_population(instance);
+        for gen in 0..self.config.generations {
+            let (offspring, objectives) = self.create_offspring(instance);
+            self.select_next_population(offspring, objectives);
+
+            if gen % (self.config.generations.div_ceil(10)) == 0 {
+                let front_size = self.rank.iter().filter(|&&r| r == 0).count();
+                let min_area = self.objectives.iter().map(|o| o.0).fold(f64::MAX, f64::min);
+                let min_wire = self.objectives.iter().map(|o| o.1).fold(f64::MAX, f64::min);
+                eprintln!("gen {}, front size {}, min area {}, min wire {}", gen, front_size, min_area, min_wire);
+            }
+        }
+
+        let mut front: Vec<(S, Objectives)> = (0..self.population.len())
+            .filter(|&i| self.rank[i] == 0)
+            .map(|i| (self.population[i].clone(), self.objectives[i]))
+            .collect();
+        front.sort_by(|a, b| a.1.0.total_cmp(&b.1.0).then(a.1.1.total_cmp(&b.1.1)));
+        // solutions with the same objectives are drawn only once
+        front.dedup_by(|a, b| a.1 == b.1);
+        front
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_non_dominated_sort() {
+        let objectives: Vec<Objectives> = vec![(1.0, 5.0), (2.0, 2.0), (3.0, 3.0), (5.0, 1.0), (4.0, 4.0), (2.0, 2.0)];
+        assert_eq!(non_dominated_sort(&objectives), vec![vec![0, 1, 3, 5], vec![2], vec![4]]);
+        let distance = crowding_distance(&objectives, &[0, 1, 3]);
+        assert_eq!(distance[0], f64::INFINITY);
+        assert_eq!(distance[1], 1.0 + 1.0);
+        assert_eq!(distance[2], f64::INFINITY);
+    }
+}
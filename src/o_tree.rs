@@ -0,0 +1,318 @@
+use crate::definitions::*;
+use crate::floorplan_common::*;
+use crate::contour::Contour;
+use crate::crossover::*;
+use rand::prelude::*;
+
+// ordered children of each slot (slot n is the root at the left chip boundary), module in each slot, rotated rectangles
+pub type OTreeSolution = (Vec<Vec<usize>>, Vec<Int>, Vec<Rectangle>);
+
+#[derive(Debug)]
+pub enum OTMoveType {
+    RotateModule(usize),
+    SwapModules(usize, usize), // slots
+    MoveModule(usize, usize, usize), // slot, new parent slot, position among its children
+}
+
+/// horizontal O-tree (Guo, Cheng, Yoshimura)
+/// a child is placed adjacent to the right of its parent, siblings are packed in DFS order on the contour
+#[derive(Debug, Default)]
+pub struct OTree {
+    modules: Vec<Rectangle>,
+    nets: Vec<Net>,
+    children: Vec<Vec<usize>>,
+    parent: Vec<usize>,
+    module_at: Vec<Int>, // module placed in slot i
+    backup: Vec<Vec<usize>>, // children before the last MoveModule
+    placement: Floorplan,
+    contour: Contour,
+    stack: Vec<usize>,
+
+    cost_function: CostFunction,
+    current_cost: f64,
+    current_wire: f64,
+    current_area: f64,
+    bounding_box: Rectangle,
+}
+
+impl OTree {
+    pub fn new(modules: Vec<Rectangle>, nets: Vec<Net>, alpha: f64) -> Self {
+        let n = modules.len();
+
+        // initial tree: all modules are children of the root, stacked in one column
+        let mut children = vec![Vec::new(); n + 1];
+        children[n] = (0..n).collect();
+        let mut ot = OTree {
+            placement: vec![(0, 0, Rectangle::new(0, 0)); n],
+            modules,
+            nets,
+            children,
+            parent: vec![n; n + 1],
+            module_at: (0..n).collect(),
+            cost_function: CostFunction::new(alpha, 1.0, 1.0),
+            ..Default::default()
+        };
+
+        // update cost parameter
+        ot.update();
+        ot.update_cost_function();
+
+        ot
+    }
+
+    pub fn update(&mut self) {
+        self.compute_floorplan();
+        self.current_area = self.bounding_box.area() as f64;
+        self.current_wire = CostFunction::compute_wirelength(&self.placement, &self.nets);
+        self.current_cost = self.cost_function.get_cost(self.current_area, self.current_wire, &self.placement);
+    }
+
+    pub fn update_cost_function(&mut self) {
+        // compute averages for cost function
+        let repetitions = 3 * self.modules.len();
+        let samples = sample_perturbations(self, repetitions, self.cost_function.has_terms());
+        self.cost_function.update_averages(&samples);
+        // perturbations are reversed without update
+        self.update();
+    }
+
+    /// registers additional weighted cost terms and re-estimates their averages
+    pub fn set_cost_terms(&mut self, terms: Vec<WeightedCostTerm>) {
+        self.cost_function.set_terms(terms);
+        self.update_cost_function();
+    }
+
+    /// packs the modules in DFS order, y-coordinates are given by the contour
+    pub fn compute_floorplan(&mut self) {
+        let root = self.modules.len();
+        self.contour.clear();
+        self.stack.extend(self.children[root].iter().rev());
+        while let Some(v) = self.stack.pop() {
+            let id = self.module_at[v];
+            let rect = self.modules[id];
+            let parent = self.parent[v];
+            let x = if parent == root {
+                0
+            }
+            else {
+                let (parent_x, _, parent_rect) = self.placement[self.module_at[parent]];
+                parent_x + parent_rect.width
+            };
+            let y = self.contour.place(x, rect.width, rect.height);
+            self.placement[id] = (x, y, rect);
+            self.stack.extend(self.children[v].iter().rev());
+        }
+
+        // compute bounding box
+        self.bounding_box.width = self.placement.iter().map(|(x, _, r)| x + r.width).max().unwrap();
+        self.bounding_box.height = self.placement.iter().map(|(_, y, r)| y + r.height).max().unwrap();
+    }
+
+    fn compute_parents(&mut self) {
+        self.parent.resize(self.children.len(), self.modules.len());
+        for v in 0..self.children.len() {
+            for &w in self.children[v].iter() {
+                self.parent[w] = v;
+            }
+        }
+    }
+
+    // the children of v take its place among the children of its parent
+    fn delete(&mut self, v: usize) {
+        let parent = self.parent[v];
+        let pos = self.children[parent].iter().position(|&w| w == v).unwrap();
+        let children = std::mem::take(&mut self.children[v]);
+        for &w in children.iter() {
+            self.parent[w] = parent;
+        }
+        self.children[parent].splice(pos..pos + 1, children);
+    }
+
+    fn insert(&mut self, v: usize, parent: usize, pos: usize) {
+        let pos = pos.min(self.children[parent].len());
+        self.children[parent].insert(pos, v);
+        self.parent[v] = parent;
+    }
+
+    // slots in DFS order
+    fn preorder(children: &[Vec<usize>]) -> Vec<usize> {
+        let mut order: Vec<usize> = Vec::with_capacity(children.len() - 1);
+        let mut stack: Vec<usize> = children[children.len() - 1].iter().rev().copied().collect();
+        while let Some(v) = stack.pop() {
+            order.push(v);
+            stack.extend(children[v].iter().rev());
+        }
+        order
+    }
+}
+
+impl Mutation<OTMoveType> for OTree {
+    fn get_random_move(&mut self) -> OTMoveType {
+        let mut rng: ThreadRng = rand::thread_rng();
+        let n = self.modules.len();
+        let r = if n < 2 { 0 } else { rng.gen_range(0..3) };
+        let a = rng.gen_range(0..n);
+        let move_type: OTMoveType = match r {
+            0 => OTMoveType::RotateModule(a),
+            1 => OTMoveType::SwapModules(a, (a + rng.gen_range(1..n)) % n),
+            _ => {
+                // new parent is any other slot or the root
+                let parent = (a + rng.gen_range(1..n + 1)) % (n + 1);
+                let pos = rng.gen_range(0..=self.children[parent].len());
+                OTMoveType::MoveModule(a, parent, pos)
+            }
+        };
+        move_type
+    }
+
+    // update = false reverses the previously applied move
+    fn apply_move(&mut self, _move: &OTMoveType, update: bool) {
+        match *_move {
+            OTMoveType::RotateModule(a) => self.modules[a] = self.modules[a].transpose(),
+            OTMoveType::SwapModules(a, b) => self.module_at.swap(a, b),
+            OTMoveType::MoveModule(v, parent, pos) => {
+                if update {
+                    self.backup.clone_from(&self.children);
+                    self.delete(v);
+                    self.insert(v, parent, pos);
+                }
+                else {
+                    std::mem::swap(&mut self.children, &mut self.backup);
+                    self.compute_parents();
+                }
+            }
+        }
+        if update {
+            self.update()
+        }
+    }
+}
+
+impl FloorCost for OTree {
+    fn get_floor_area(&self) -> f64 {
+        self.current_area
+    }
+
+    fn get_floor_wire(&self) -> f64 {
+        self.current_wire
+    }
+}
+
+impl FloorPlan for OTree {
+    fn get_floorplan(&self) -> Floorplan {
+        self.placement.clone()
+    }
+}
+
+impl Cost for OTree {
+    fn get_cost(&self) -> f64 {
+        self.current_cost
+    }
+}
+
+impl Solution<OTreeSolution> for OTree {
+    fn copy_solution(&self) -> OTreeSolution {
+        (self.children.clone(), self.module_at.clone(), self.modules.clone())
+    }
+
+    fn set_solution(&mut self, solution: OTreeSolution) {
+        (self.children, self.module_at, self.modules) = solution;
+        self.compute_parents();
+        self.update()
+    }
+}
+
+impl RandomSolution<OTreeSolution> for OTree {
+    fn random_solution(&self) -> OTreeSolution {
+        let mut rng: ThreadRng = rand::thread_rng();
+        let n = self.modules.len();
+        // attach each slot at a random position below the root or a previous slot
+        let mut children: Vec<Vec<usize>> = vec![Vec::new(); n + 1];
+        for v in 0..n {
+            let parent = if rng.gen_range(0..=v) == v { n } else { rng.gen_range(0..v) };
+            let pos = rng.gen_range(0..=children[parent].len());
+            children[parent].insert(pos, v);
+        }
+        let mut module_at: Vec<Int> = (0..n).collect();
+        module_at.shuffle(&mut rng);
+        let mut rect = self.modules.clone();
+        for r in rect.iter_mut() {
+            if rng.gen_bool(0.5) {
+                *r = r.transpose();
+            }
+        }
+        (children, module_at, rect)
+    }
+}
+
+impl Crossover<OTreeSolution> for OTree {
+    // keeps the tree of a and fills its slots in DFS order with the order crossover of the DFS module sequences
+    fn crossover(&self, a: &OTreeSolution, b: &OTreeSolution) -> OTreeSolution {
+        let slots_a = OTree::preorder(&a.0);
+        let slots_b = OTree::preorder(&b.0);
+        let sequence_a: Vec<Int> = slots_a.iter().map(|&v| a.1[v]).collect();
+        let sequence_b: Vec<Int> = slots_b.iter().map(|&v| b.1[v]).collect();
+
+        let (sequence, from_a) = order_crossover(&sequence_a, &sequence_b, random_cut(sequence_a.len()));
+        let mut module_at = a.1.clone();
+        for (&v, &id) in slots_a.iter().zip(sequence.iter()) {
+            module_at[v] = id;
+        }
+        let rect: Vec<Rectangle> = (0..from_a.len())
+            .map(|i| if from_a[i] { a.2[i] } else { b.2[i] })
+            .collect();
+        (a.0.clone(), module_at, rect)
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compaction::find_overlaps;
+
+    fn random_modules(n: usize) -> Vec<Rectangle> {
+        let mut rng = StdRng::seed_from_u64(0);
+        (0..n).map(|_| Rectangle::new(rng.gen_range(1..10), rng.gen_range(1..10))).collect()
+    }
+
+    #[test]
+    fn test_pack() {
+        // 2 right of its parent 0, 1 is the second child of the root and lies on top of both
+        let modules = vec![Rectangle::new(2, 2), Rectangle::new(3, 1), Rectangle::new(1, 3)];
+        let mut ot = OTree::new(modules.clone(), Vec::new(), 1.0);
+        let children = vec![vec![2], Vec::new(), Vec::new(), vec![0, 1]];
+        assert_eq!(OTree::preorder(&children), vec![0, 2, 1]);
+        ot.set_solution((children, vec![0, 1, 2], modules));
+        assert_eq!(ot.get_floorplan(), vec![
+            (0, 0, Rectangle::new(2, 2)),
+            (0, 3, Rectangle::new(3, 1)),
+            (2, 0, Rectangle::new(1, 3)),
+        ]);
+        assert_eq!(ot.get_floor_area(), 12.0);
+    }
+
+    #[test]
+    fn test_moves() {
+        let n = 15;
+        let mut ot = OTree::new(random_modules(n), Vec::new(), 1.0);
+        let moves = [OTMoveType::RotateModule(3), OTMoveType::SwapModules(0, 7), OTMoveType::MoveModule(2, 9, 0), OTMoveType::MoveModule(9, n, 1)];
+        for _move in moves.iter().chain((0..200).map(|_| ot.get_random_move()).collect::<Vec<_>>().iter()) {
+            let solution = ot.copy_solution();
+            ot.apply_move(_move, true);
+            assert!(find_overlaps(&ot.get_floorplan()).is_empty());
+            assert_eq!(OTree::preorder(&ot.children).len(), n);
+            ot.apply_move(_move, false);
+            assert_eq!(ot.copy_solution(), solution);
+            // keep the move to walk through different trees
+            ot.apply_move(_move, true);
+        }
+    }
+
+    #[test]
+    fn test_random_solution() {
+        let mut ot = OTree::new(random_modules(15), Vec::new(), 1.0);
+        for _ in 0..20 {
+            ot.set_solution(ot.random_solution());
+            assert!(find_overlaps(&ot.get_floorplan()).is_empty());
+        }
+    }
+}
@@ -1,18 +1,21 @@
 use crate::definitions::*;
+use crate::constraints::Constraints;
 use std::fs::File;
 use std::path::Path;
 use std::io::{self, prelude::*, BufReader};
 
+// comment line of the optimal bounding box of generated instances
+const OPTIMUM_COMMENT: &str = "# optimum ";
+
 fn parse_ints(s: &String) -> Vec<Int> {
     s.split(" ").map(|x|  x.parse::<Int>().unwrap()).collect()
 }
 
-pub fn parse_file<P>(file_path: P) -> io::Result<(Vec<Rectangle>, Vec<Net>)>
+// lines without comments, number of blocks and nets
+fn read_lines<P>(file_path: P) -> io::Result<(Vec<String>, usize, usize)>
 where P: AsRef<Path>, {
     let file = BufReader::new(File::open(file_path).unwrap());
     let mut lines: Vec<String> = Vec::new();
-    let mut blocks: Vec<Rectangle> = Vec::new();
-    let mut nets: Vec<Net> = Vec::new();
     for line in file.lines() {
         let s = line?;
         if !s.starts_with("#") {
@@ -22,6 +25,14 @@ where P: AsRef<Path>, {
     let (n, m) = lines[0].split_once(' ').unwrap();
     let num_blocks = n.parse::<usize>().unwrap();
     let num_nets = m.parse::<usize>().unwrap();
+    Ok((lines, num_blocks, num_nets))
+}
+
+pub fn parse_file<P>(file_path: P) -> io::Result<(Vec<Rectangle>, Vec<Net>)>
+where P: AsRef<Path>, {
+    let (lines, num_blocks, num_nets) = read_lines(file_path)?;
+    let mut blocks: Vec<Rectangle> = Vec::new();
+    let mut nets: Vec<Net> = Vec::new();
     for i in 0..num_blocks {
         let width_height = parse_ints(&lines[i + 1]);
         blocks.push(Rectangle::new(width_height[0], width_height[1]));
@@ -31,4 +42,69 @@ where P: AsRef<Path>, {
         nets.push(Net::new(pins, i));
     }
     Ok((blocks, nets))
-}
\ No newline at end of file
+}
+
+/// writes modules and nets in the format read by parse_file, a known optimal bounding box is written as comment
+pub fn write_file<P>(file_path: P, blocks: &[Rectangle], nets: &[Net], optimum: Option<Rectangle>) -> io::Result<()>
+where P: AsRef<Path>, {
+    let mut file = File::create(file_path)?;
+    writeln!(file, "# num_blocks num_nets")?;
+    writeln!(file, "# width height (num_blocks times)")?;
+    writeln!(file, "# id1 id2 ... (num_nets times)")?;
+    if let Some(rect) = optimum {
+        writeln!(file, "{}{} {}", OPTIMUM_COMMENT, rect.width, rect.height)?;
+    }
+    writeln!(file, "{} {}", blocks.len(), nets.len())?;
+    for rect in blocks.iter() {
+        writeln!(file, "{} {}", rect.width, rect.height)?;
+    }
+    for net in nets.iter() {
+        let pins: Vec<String> = net.pins.iter().map(|pin| pin.to_string()).collect();
+        writeln!(file, "{}", pins.join(" "))?;
+    }
+    Ok(())
+}
+
+/// optimal bounding box of a generated instance if it is known
+pub fn parse_optimum<P>(file_path: P) -> io::Result<Option<Rectangle>>
+where P: AsRef<Path>, {
+    let file = BufReader::new(File::open(file_path)?);
+    for line in file.lines() {
+        if let Some(size) = line?.strip_prefix(OPTIMUM_COMMENT) {
+            let width_height = parse_ints(&size.to_string());
+            return Ok(Some(Rectangle::new(width_height[0], width_height[1])));
+        }
+    }
+    Ok(None)
+}
+
+/// parses the optional constraint lines after the net list
+pub fn parse_constraints<P>(file_path: P) -> io::Result<Constraints>
+where P: AsRef<Path>, {
+    let (lines, num_blocks, num_nets) = read_lines(file_path)?;
+    let mut constraints = Constraints::default();
+    for line in lines[1 + num_blocks + num_nets..].iter() {
+        constraints.parse_line(line, num_blocks);
+    }
+    Ok(constraints)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_write_file() {
+        let path = std::env::temp_dir().join(format!("floorplanning_test_{}.floor", std::process::id()));
+        let blocks = vec![Rectangle::new(3, 4), Rectangle::new(5, 1), Rectangle::new(2, 2)];
+        let nets = vec![Net::new(vec![0, 1], 0), Net::new(vec![2, 0, 1], 1)];
+        let pins = |nets: &[Net]| nets.iter().map(|net| net.pins.clone()).collect::<Vec<Vec<usize>>>();
+        for optimum in [Some(Rectangle::new(7, 3)), None] {
+            write_file(&path, &blocks, &nets, optimum).unwrap();
+            let (parsed_blocks, parsed_nets) = parse_file(&path).unwrap();
+            assert_eq!((parsed_blocks, pins(&parsed_nets)), (blocks.clone(), pins(&nets)));
+            assert_eq!(parse_optimum(&path).unwrap(), optimum);
+        }
+        std::fs::remove_file(&path).unwrap();
+    }
+}
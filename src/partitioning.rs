@@ -0,0 +1,306 @@
+use crate::definitions::*;
+use crate::hypergraph::Hypergraph;
+
+// maximum deviation of the area of a side from half of the total area
+const BALANCE_TOLERANCE: f64 = 0.1;
+// FM passes are repeated until the cut does not improve or this limit is reached
+const MAX_PASSES: usize = 10;
+
+/// nodes sorted into buckets by their integer gain, the node of highest gain is found in amortized O(1)
+pub struct GainBuckets {
+    buckets: Vec<Vec<Int>>,
+    gain: Vec<i64>,
+    position: Vec<usize>, // position of a node in its bucket, usize::MAX if not contained
+    max_gain: i64,
+    max_bucket: usize,    // upper bound of the highest non-empty bucket
+}
+
+impl GainBuckets {
+    /// gains are in [-max_gain, max_gain]
+    pub fn new(num_nodes: usize, max_gain: usize) -> Self {
+        GainBuckets {
+            buckets: vec![Vec::new(); 2 * max_gain + 1],
+            gain: vec![0; num_nodes],
+            position: vec![usize::MAX; num_nodes],
+            max_gain: max_gain as i64,
+            max_bucket: 0,
+        }
+    }
+
+    fn bucket(&self, gain: i64) -> usize {
+        (gain + self.max_gain) as usize
+    }
+
+    pub fn contains(&self, v: Int) -> bool {
+        self.position[v] != usize::MAX
+    }
+
+    pub fn get_gain(&self, v: Int) -> i64 {
+        self.gain[v]
+    }
+
+    pub fn insert(&mut self, v: Int, gain: i64) {
+        let b = self.bucket(gain);
+        self.gain[v] = gain;
+        self.position[v] = self.buckets[b].len();
+        self.buckets[b].push(v);
+        self.max_bucket = self.max_bucket.max(b);
+    }
+
+    pub fn remove(&mut self, v: Int) {
+        let b = self.bucket(self.gain[v]);
+        let pos = self.position[v];
+        self.buckets[b].swap_remove(pos);
+        if pos < self.buckets[b].len() {
+            let w = self.buckets[b][pos];
+            self.position[w] = pos;
+        }
+        self.position[v] = usize::MAX;
+    }
+
+    /// changes the gain of a contained node by delta
+    pub fn update(&mut self, v: Int, delta: i64) {
+        let gain = self.gain[v] + delta;
+        self.remove(v);
+        self.insert(v, gain);
+    }
+
+    /// nodes of highest gain
+    pub fn top_bucket(&mut self) -> &[Int] {
+        while self.max_bucket > 0 && self.buckets[self.max_bucket].is_empty() {
+            self.max_bucket -= 1;
+        }
+        &self.buckets[self.max_bucket]
+    }
+
+    /// node of highest gain that is accepted, the buckets are scanned from the highest gain
+    pub fn find_max(&mut self, accept: impl Fn(Int) -> bool) -> Option<Int> {
+        self.top_bucket();
+        (0..=self.max_bucket).rev()
+            .find_map(|b| self.buckets[b].iter().rev().copied().find(|&v| accept(v)))
+    }
+}
+
+/// Fiduccia-Mattheyses bipartitioning of the given nodes minimizing the number of cut nets,
+/// the area of each side deviates at most by the tolerance or the largest module from half of the total area
+/// nets are restricted to the given nodes, returns both sides
+fn fm_bipartition(graph: &Hypergraph, areas: &[Int], nodes: &[Int]) -> (Vec<Int>, Vec<Int>) {
+    let k = nodes.len();
+    let mut local: Vec<usize> = vec![usize::MAX; areas.len()];
+    for (i, &v) in nodes.iter().enumerate() {
+        local[v] = i;
+    }
+    // nets with at least two pins among the nodes
+    let mut nets: Vec<Vec<usize>> = Vec::new();
+    let mut nets_of: Vec<Vec<usize>> = vec![Vec::new(); k];
+    let mut visited: Vec<bool> = vec![false; graph.num_nets];
+    for &v in nodes.iter() {
+        for &e in graph.nets(v).iter() {
+            if visited[e] {
+                continue;
+            }
+            visited[e] = true;
+            let pins: Vec<usize> = graph.pins(e).iter().map(|&w| local[w]).filter(|&i| i != usize::MAX).collect();
+            if pins.len() > 1 {
+                for &i in pins.iter() {
+                    nets_of[i].push(nets.len());
+                }
+                nets.push(pins);
+            }
+        }
+    }
+
+    let area: Vec<Int> = nodes.iter().map(|&v| areas[v]).collect();
+    let total: Int = area.iter().sum();
+    let tolerance = (BALANCE_TOLERANCE * total as f64) as Int;
+    let slack = tolerance.max(*area.iter().max().unwrap());
+    let lower = (total / 2).saturating_sub(slack);
+    let upper = total / 2 + slack;
+
+    // initial partition: the first nodes up to half of the area are on side 0
+    let mut side: Vec<usize> = vec![1; k];
+    let mut side_area: [Int; 2] = [0, total];
+    for i in 0..k {
+        if side_area[0] >= total / 2 {
+            break;
+        }
+        side[i] = 0;
+        side_area[0] += area[i];
+        side_area[1] -= area[i];
+    }
+
+    let max_degree = nets_of.iter().map(|e| e.len()).max().unwrap_or(0);
+    let mut count: Vec<[usize; 2]> = vec![[0, 0]; nets.len()];
+    for pass in 0..MAX_PASSES {
+        for (e, pins) in nets.iter().enumerate() {
+            count[e] = [0, 0];
+            for &i in pins.iter() {
+                count[e][side[i]] += 1;
+            }
+        }
+        // gain = nets leaving the cut - nets entering the cut
+        let mut buckets = GainBuckets::new(k, max_degree);
+        for i in 0..k {
+            let from = side[i];
+            let gain: i64 = nets_of[i].iter()
+                .map(|&e| (count[e][from] == 1) as i64 - (count[e][1 - from] == 0) as i64)
+                .sum();
+            buckets.insert(i, gain);
+        }
+
+        let mut moves: Vec<usize> = Vec::new();
+        let mut delta: i64 = 0;
+        let mut best_delta: i64 = 0;
+        let mut best_moves: usize = 0;
+        loop {
+            let feasible = |i: usize| side_area[side[i]] - area[i] >= lower && side_area[1 - side[i]] + area[i] <= upper;
+            let Some(i) = buckets.find_max(feasible) else { break };
+            delta -= buckets.get_gain(i);
+            buckets.remove(i);
+            let (from, to) = (side[i], 1 - side[i]);
+            for &e in nets_of[i].iter() {
+                // critical nets before the move
+                if count[e][to] == 0 {
+                    for &j in nets[e].iter() {
+                        if buckets.contains(j) {
+                            buckets.update(j, 1);
+                        }
+                    }
+                }
+                else if count[e][to] == 1 {
+                    if let Some(&j) = nets[e].iter().find(|&&j| side[j] == to && buckets.contains(j)) {
+                        buckets.update(j, -1);
+                    }
+                }
+                count[e][from] -= 1;
+                count[e][to] += 1;
+                // critical nets after the move
+                if count[e][from] == 0 {
+                    for &j in nets[e].iter() {
+                        if buckets.contains(j) {
+                            buckets.update(j, -1);
+                        }
+                    }
+                }
+                else if count[e][from] == 1 {
+                    if let Some(&j) = nets[e].iter().find(|&&j| j != i && side[j] == from && buckets.contains(j)) {
+                        buckets.update(j, 1);
+                    }
+                }
+            }
+            side[i] = to;
+            side_area[from] -= area[i];
+            side_area[to] += area[i];
+            moves.push(i);
+            if delta < best_delta {
+                best_delta = delta;
+                best_moves = moves.len();
+            }
+        }
+        // undo the moves after the best cut
+        for &i in moves[best_moves..].iter() {
+            side_area[side[i]] -= area[i];
+            side[i] = 1 - side[i];
+            side_area[side[i]] += area[i];
+        }
+        if best_delta == 0 {
+            break;
+        }
+        if pass + 1 == MAX_PASSES {
+            eprintln!("FM stopped after {} passes", MAX_PASSES);
+        }
+    }
+
+    let (mut a, mut b): (Vec<Int>, Vec<Int>) = (Vec::new(), Vec::new());
+    for i in 0..k {
+        if side[i] == 0 { a.push(nodes[i]) } else { b.push(nodes[i]) }
+    }
+    (a, b)
+}
+
+fn min_cut_partition(graph: &Hypergraph, areas: &[Int], nodes: Vec<Int>, order: &mut Vec<Int>, cuts: &mut Vec<usize>) {
+    if nodes.len() <= 2 {
+        order.extend(nodes);
+        return;
+    }
+    let (mut a, mut b) = fm_bipartition(graph, areas, &nodes);
+    if a.is_empty() || b.is_empty() {
+        // a single module exceeds the balance, split by count
+        a = nodes[..nodes.len().div_ceil(2)].to_vec();
+        b = nodes[nodes.len().div_ceil(2)..].to_vec();
+    }
+    cuts.push(order.len() + a.len());
+    min_cut_partition(graph, areas, a, order, cuts);
+    min_cut_partition(graph, areas, b, order, cuts);
+}
+
+/// recursive min-cut bisection of all modules,
+/// returns the module order and the cut position of each part with more than two modules in preorder
+pub fn min_cut_bisection(graph: &Hypergraph, modules: &[Rectangle]) -> (Vec<Int>, Vec<usize>) {
+    let n = modules.len();
+    let areas: Vec<Int> = modules.iter().map(|rect| rect.area()).collect();
+    let mut order: Vec<Int> = Vec::with_capacity(n);
+    let mut cuts: Vec<usize> = Vec::new();
+    min_cut_partition(graph, &areas, (0..n).collect(), &mut order, &mut cuts);
+    (order, cuts)
+}
+
+/// cut positions of the bisection of the order into parts of about half of the area, in preorder as in min_cut_bisection
+pub fn area_balanced_cuts(order: &[Int], modules: &[Rectangle]) -> Vec<usize> {
+    fn visit(prefix_area: &[Int], left: usize, right: usize, cuts: &mut Vec<usize>) {
+        if right - left > 2 {
+            // area of [left, mid) closest to half of the area of [left, right)
+            let total = prefix_area[right] - prefix_area[left];
+            let mid = (left + 1..right)
+                .min_by_key(|&mid| (2 * (prefix_area[mid] - prefix_area[left])).abs_diff(total))
+                .unwrap();
+            cuts.push(mid);
+            visit(prefix_area, left, mid, cuts);
+            visit(prefix_area, mid, right, cuts);
+        }
+    }
+    let mut prefix_area: Vec<Int> = vec![0; order.len() + 1];
+    for (i, &v) in order.iter().enumerate() {
+        prefix_area[i + 1] = prefix_area[i] + modules[v].area();
+    }
+    let mut cuts: Vec<usize> = Vec::new();
+    visit(&prefix_area, 0, order.len(), &mut cuts);
+    cuts
+}
+
+/// number of nets with pins on both sides of the cut
+pub fn get_cut_size(graph: &Hypergraph, side: &[bool]) -> usize {
+    (0..graph.num_nets).map(|e| graph.pins(e))
+        .filter(|pins| pins.iter().any(|&v| side[v]) && pins.iter().any(|&v| !side[v]))
+        .count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fm_bipartition() {
+        // two cliques of four modules connected by one net
+        let mut nets: Vec<Net> = Vec::new();
+        for clique in [[0, 2, 4, 6], [1, 3, 5, 7]] {
+            for i in 0..4 {
+                for j in i + 1..4 {
+                    nets.push(Net::new(vec![clique[i], clique[j]], nets.len()));
+                }
+            }
+        }
+        nets.push(Net::new(vec![6, 7], nets.len()));
+        let graph = Hypergraph::new(8, &nets);
+        let modules = vec![Rectangle::new(1, 1); 8];
+        let (order, cuts) = min_cut_bisection(&graph, &modules);
+        assert_eq!(cuts[0], 4);
+        let mut side: Vec<bool> = vec![false; 8];
+        for &v in order[..4].iter() {
+            side[v] = true;
+        }
+        assert_eq!(get_cut_size(&graph, &side), 1);
+        let modules = vec![Rectangle::new(4, 4), Rectangle::new(1, 1), Rectangle::new(1, 1), Rectangle::new(2, 2), Rectangle::new(2, 2)];
+        assert_eq!(area_balanced_cuts(&[0, 1, 2, 3, 4], &modules), vec![1, 4, 3]);
+    }
+}
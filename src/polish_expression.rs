@@ -1,6 +1,7 @@
 use crate::definitions::*;
 use crate::slicing_tree::*;
 use crate::floorplan_common::*;
+use crate::crossover::*;
 use rand::prelude::*;
 
 pub type PolishExpressionSolution = Vec<ModuleNode>;
@@ -31,6 +32,32 @@ impl PEMoveType {
     }
 }
 
+/// checks the balloting property (more operands than operators in every prefix),
+/// that every module occurs once and that there are no consecutive equal operators
+pub fn is_normalized(solution: &PolishExpressionSolution, n: usize) -> bool {
+    let mut seen = vec![false; n];
+    let mut operands: usize = 0;
+    let mut operators: usize = 0;
+    for (i, node) in solution.iter().enumerate() {
+        match *node {
+            ModuleNode::Module(id) => {
+                if id >= n || seen[id] {
+                    return false;
+                }
+                seen[id] = true;
+                operands += 1;
+            }
+            _ => {
+                operators += 1;
+                if operators >= operands || (i > 0 && solution[i - 1] == *node) {
+                    return false;
+                }
+            }
+        }
+    }
+    operands == n && operators + 1 == n
+}
+
 #[derive(Default)]
 pub struct PolishExpression {
     solution: PolishExpressionSolution,
@@ -38,6 +65,7 @@ pub struct PolishExpression {
     nets: Vec<Net>,
     num_operators: Vec<usize>, // to check if op3 is legal
     tree: SlicingTree,
+    realization: Realization,
 
     cost_function: CostFunction,
     current_cost: f64,
@@ -58,30 +86,75 @@ impl PolishExpression {
         pe.cost_function = CostFunction::new(alpha, 1.0, 1.0);
 
         pe.set_solution_all_vertical();
+        pe.update_cost_function();
         pe
     }
 
+    /// the shapes change area and wirelength of all floorplans, so the averages of the cost function are re-estimated
     pub fn set_module_shape(&mut self, module_shape: ModuleShape) {
-        self.tree.set_module_shape(module_shape)
+        self.tree.set_module_shape(module_shape);
+        self.update_cost_function();
+    }
+
+    /// the realization changes area and wirelength of all floorplans, so the averages of the cost function are re-estimated
+    pub fn set_realization(&mut self, realization: Realization) {
+        self.realization = realization;
+        self.update_cost_function();
     }
 
     pub fn update_cost_function(&mut self) {
         // compute averages for cost function
         let repetitions = 3 * self.modules.len();
-        let (avg_wirelength,avg_area) = CostFunction::compute_mean_parameters(self, repetitions);
-        self.cost_function = CostFunction::new(self.cost_function.alpha, avg_wirelength, avg_area);
-        self.current_cost = self.cost_function.get_cost(self.current_area, self.current_wire);
+        let samples = sample_perturbations(self, repetitions, self.cost_function.has_terms());
+        self.cost_function.update_averages(&samples);
+        // perturbations are reversed without update
+        self.update();
+    }
+
+    /// registers additional weighted cost terms and re-estimates their averages
+    pub fn set_cost_terms(&mut self, terms: Vec<WeightedCostTerm>) {
+        self.cost_function.set_terms(terms);
+        self.update_cost_function();
     }
 
     pub fn update(&mut self) {
         self.tree.recompute(&self.solution, &self.modules);
-        self.tree.recompute_floorplan();
-        self.current_area = self.tree.get_min_area();
-        self.current_wire = CostFunction::compute_wirelength(&self.tree.placement, &self.nets);
-        self.current_cost = self.cost_function.get_cost(self.current_area, self.current_wire);
+        match self.realization {
+            Realization::MinArea() => {
+                self.tree.recompute_floorplan();
+                self.current_area = self.tree.get_min_area();
+                self.current_wire = CostFunction::compute_wirelength(&self.tree.placement, &self.nets);
+            }
+            Realization::MinWire(tolerance) => self.realize_min_wire(tolerance),
+        }
+        self.current_cost = self.cost_function.get_cost(self.current_area, self.current_wire, &self.tree.placement);
         self.num_operators = self.get_num_operator();   
     }
 
+    // among the near-minimal root shapes realize the one with least wirelength
+    fn realize_min_wire(&mut self, tolerance: f64) {
+        let shapes = self.tree.get_near_minimal_shapes(tolerance);
+        let mut best: Option<(f64, Int, Rectangle)> = None;
+        for rect in shapes.iter() {
+            self.tree.recompute_floorplan_with_root(*rect);
+            let wire = CostFunction::compute_wirelength(&self.tree.placement, &self.nets);
+            let is_better = match best {
+                None => true,
+                Some((best_wire, best_area, _)) => wire < best_wire || (wire == best_wire && rect.area() < best_area),
+            };
+            if is_better {
+                best = Some((wire, rect.area(), *rect));
+            }
+        }
+        let (wire, area, rect) = best.expect("root shape function is empty");
+        // placement belongs to the last realized shape
+        if rect != *shapes.last().unwrap() {
+            self.tree.recompute_floorplan_with_root(rect);
+        }
+        self.current_area = area as f64;
+        self.current_wire = wire;
+    }
+
     pub fn set_solution_all_vertical(&mut self) {
         let mut solution: Vec<ModuleNode> = vec![ModuleNode::Module(0)];
         for i in 1..self.modules.len() {
@@ -91,27 +164,94 @@ impl PolishExpression {
         self.set_solution(solution);
     }
 
-    pub fn set_solution_recursive_bisection(&mut self, order: &Vec<Int>) {
-        let solution = self.recursive_bisection(&order, 0, self.modules.len(), ModuleNode::H());
+    /// cuts are the split positions of the parts with more than two modules in preorder
+    /// the orientation of each cut is chosen by the shape of the region, starting with a square of the module area
+    pub fn set_solution_recursive_bisection(&mut self, order: &Vec<Int>, cuts: &[usize]) {
+        let side = (self.modules.iter().map(|rect| rect.area()).sum::<Int>() as f64).sqrt();
+        let solution = self.recursive_bisection(order, &mut cuts.iter(), 0, self.modules.len(), (side, side));
         self.set_solution(solution);
+        self.update_cost_function();
     }
 
     // intervall [l, r)
-    fn recursive_bisection(&self, order: &Vec<Int>, left: usize, right: usize, split: ModuleNode) -> PolishExpressionSolution {
+    fn recursive_bisection(&self, order: &Vec<Int>, cuts: &mut std::slice::Iter<usize>, left: usize, right: usize, region: (f64, f64)) -> PolishExpressionSolution {
         if right - left == 1 {
             return vec![ModuleNode::Module(order[left])];
         }
-        else if right - left == 2 {
-            return vec![ModuleNode::Module(order[left]), ModuleNode::Module(order[left + 1]), split];
+        let mid = if right - left == 2 { left + 1 } else { *cuts.next().unwrap() };
+        let area = |l: usize, r: usize| order[l..r].iter().map(|&i| self.modules[i].area()).sum::<Int>() as f64;
+        let (side_by_side, region1, region2) = split_region(region, area(left, mid) / area(left, right));
+        let split = if side_by_side { ModuleNode::V() } else { ModuleNode::H() };
+        let s1 = self.recursive_bisection(order, cuts, left, mid, region1);
+        let s2 = self.recursive_bisection(order, cuts, mid, right, region2);
+        PolishExpression::join(s1, s2, split)
+    }
+
+    /// normalized expression of a slicing floorplan, e.g. the floorplan of another representation,
+    /// returns the modules of the first part that has no guillotine cut if the floorplan is not slicing
+    pub fn set_solution_floorplan(&mut self, plan: &Floorplan) -> Result<(), Vec<Int>> {
+        let solution = PolishExpression::slice(plan, (0..plan.len()).collect())?;
+        self.set_solution(solution);
+        self.update_cost_function();
+        Ok(())
+    }
+
+    // recursively splits the modules at the first vertical or horizontal line that does not cut any module
+    fn slice(plan: &Floorplan, modules: Vec<Int>) -> Result<PolishExpressionSolution, Vec<Int>> {
+        if modules.len() == 1 {
+            return Ok(vec![ModuleNode::Module(modules[0])]);
+        }
+        let right = |i: Int| plan[i].0 + plan[i].2.width;
+        let top = |i: Int| plan[i].1 + plan[i].2.height;
+        let mut by_x = modules.clone();
+        by_x.sort_by_key(|&i| (plan[i].0, right(i)));
+        let mut by_y = modules;
+        by_y.sort_by_key(|&i| (plan[i].1, top(i)));
+        // a cut after position k exists if no module of the first k starts after the next one ends
+        let find_cut = |sorted: &[Int], start: &dyn Fn(Int) -> usize, end: &dyn Fn(Int) -> usize| {
+            let mut max_end = 0;
+            (0..sorted.len() - 1).find(|&k| {
+                max_end = max_end.max(end(sorted[k]));
+                max_end <= start(sorted[k + 1])
+            })
+        };
+        let (sorted, k, split) = if let Some(k) = find_cut(&by_x, &|i| plan[i].0, &right) {
+            (by_x, k, ModuleNode::V())
+        }
+        else if let Some(k) = find_cut(&by_y, &|i| plan[i].1, &top) {
+            (by_y, k, ModuleNode::H())
         }
         else {
-            let mid = (left + right).div_ceil(2);
-            let mut s1 = self.recursive_bisection(order, left, mid, split.invert());
-            let mut s2 = self.recursive_bisection(order, mid, right, split.invert());
-            s1.append(&mut s2);
+            return Err(by_x);
+        };
+        let mut first = sorted;
+        let second = first.split_off(k + 1);
+        let s1 = PolishExpression::slice(plan, first)?;
+        let s2 = PolishExpression::slice(plan, second)?;
+        Ok(PolishExpression::join(s1, s2, split))
+    }
+
+    // joins two normalized expressions, a chain of equal operators is skewed to the left to stay normalized:
+    // s1 (b c split) split -> (s1 b split) c split
+    fn join(mut s1: PolishExpressionSolution, s2: PolishExpressionSolution, split: ModuleNode) -> PolishExpressionSolution {
+        if s2.last() != Some(&split) {
+            s1.extend(s2);
             s1.push(split);
             return s1;
         }
+        // c is the shortest suffix forming an expression
+        let mut balance: i32 = 0;
+        let mut start = s2.len() - 1;
+        for i in (0..s2.len() - 1).rev() {
+            balance += if s2[i].is_module() { 1 } else { -1 };
+            if balance == 1 {
+                start = i;
+                break;
+            }
+        }
+        let mut s = PolishExpression::join(s1, s2[..start].to_vec(), split);
+        s.extend_from_slice(&s2[start..]);
+        s
     }
 
     fn get_num_operator(&self) -> Vec<usize> {
@@ -263,7 +403,131 @@ impl Solution<PolishExpressionSolution> for PolishExpression {
     fn set_solution(&mut self, solution: PolishExpressionSolution) {
         self.solution = solution;
         self.tree.update_everything();
-        self.update();
-        self.update_cost_function()
+        self.update()
+    }
+}
+
+impl RandomSolution<PolishExpressionSolution> for PolishExpression {
+    // random skeleton of operands and operators satisfying the balloting property,
+    // operators are chosen so that no two consecutive operators are equal
+    fn random_solution(&self) -> PolishExpressionSolution {
+        let mut rng: ThreadRng = rand::thread_rng();
+        let n = self.modules.len();
+        let mut order: Vec<Int> = (0..n).collect();
+        order.shuffle(&mut rng);
+
+        let mut solution: PolishExpressionSolution = Vec::with_capacity(2 * n - 1);
+        let (mut operands, mut operators) = (0, 0);
+        while operands < n || operators + 1 < n {
+            let operator_allowed = operators + 1 < operands;
+            if operands < n && (!operator_allowed || rng.gen_bool(0.5)) {
+                solution.push(ModuleNode::Module(order[operands]));
+                operands += 1;
+            }
+            else {
+                let operator = match solution.last() {
+                    Some(ModuleNode::H()) => ModuleNode::V(),
+                    Some(ModuleNode::V()) => ModuleNode::H(),
+                    _ => if rng.gen_bool(0.5) { ModuleNode::H() } else { ModuleNode::V() },
+                };
+                solution.push(operator);
+                operators += 1;
+            }
+        }
+        debug_assert!(is_normalized(&solution, n));
+        solution
+    }
+}
+
+impl Crossover<PolishExpressionSolution> for PolishExpression {
+    // keeps the operators of a at their positions and fills the operand positions
+    // with the order crossover of the operand sequences, so the result stays normalized
+    fn crossover(&self, a: &PolishExpressionSolution, b: &PolishExpressionSolution) -> PolishExpressionSolution {
+        let operands = |s: &PolishExpressionSolution| -> Vec<Int> {
+            s.iter().filter_map(|node| match *node { ModuleNode::Module(id) => Some(id), _ => None }).collect()
+        };
+        let operands_a = operands(a);
+        let operands_b = operands(b);
+        let (sequence, _) = order_crossover(&operands_a, &operands_b, random_cut(operands_a.len()));
+        let mut sequence = sequence.into_iter();
+        let solution: PolishExpressionSolution = a.iter()
+            .map(|node| if node.is_module() { ModuleNode::Module(sequence.next().unwrap()) } else { *node })
+            .collect();
+        debug_assert!(is_normalized(&solution, self.modules.len()));
+        solution
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_random_solution_and_crossover() {
+        let n = 20;
+        let modules: Vec<Rectangle> = (1..=n).map(|i| Rectangle::new(i, n + 1 - i)).collect();
+        let pe = PolishExpression::new(modules, Vec::new(), 1.0);
+        for _ in 0..100 {
+            let a = pe.random_solution();
+            let b = pe.random_solution();
+            assert!(is_normalized(&a, n));
+            assert!(is_normalized(&pe.crossover(&a, &b), n));
+        }
+    }
+
+    #[test]
+    fn test_min_wire_realization() {
+        // root shapes 9x1 (area 9) and 2x5 (area 10), the narrow one has less wirelength
+        let modules = vec![Rectangle::new(1, 4), Rectangle::new(1, 5)];
+        let nets = vec![Net::new(vec![0, 1], 0)];
+        let solution = vec![ModuleNode::Module(0), ModuleNode::Module(1), ModuleNode::V()];
+        let mut pe = PolishExpression::new(modules, nets, 1.0);
+        pe.set_solution(solution.clone());
+        assert_eq!(pe.get_floor_area(), 9.0);
+        assert_eq!(pe.get_floor_wire(), 4.5);
+
+        // the 2x5 shape is within the tolerance
+        pe.set_realization(Realization::MinWire(0.2));
+        pe.set_solution(solution.clone());
+        assert_eq!(pe.get_floor_area(), 10.0);
+        assert_eq!(pe.get_floor_wire(), 1.5);
+        assert_eq!(pe.get_floorplan(), vec![(0, 0, Rectangle::new(1, 4)), (1, 0, Rectangle::new(1, 5))]);
+
+        // without tolerance only the minimum area shape is realized
+        pe.set_realization(Realization::MinWire(0.0));
+        pe.set_solution(solution);
+        assert_eq!(pe.get_floor_area(), 9.0);
+        assert_eq!(pe.get_floor_wire(), 4.5);
+    }
+
+    #[test]
+    fn test_recursive_bisection() {
+        // wide modules lead to chains of equal cuts
+        let n = 13;
+        let modules: Vec<Rectangle> = (1..=n).map(|i| Rectangle::new(10 * i, 1)).collect();
+        let mut pe = PolishExpression::new(modules.clone(), Vec::new(), 1.0);
+        let order: Vec<Int> = (0..n).collect();
+        pe.set_solution_recursive_bisection(&order, &crate::partitioning::area_balanced_cuts(&order, &modules));
+        assert!(is_normalized(&pe.copy_solution(), n));
+        let joined = PolishExpression::join(
+            vec![ModuleNode::Module(0)],
+            vec![ModuleNode::Module(1), ModuleNode::Module(2), ModuleNode::V(), ModuleNode::Module(3), ModuleNode::Module(4), ModuleNode::H(), ModuleNode::V()],
+            ModuleNode::V());
+        let expected = vec![ModuleNode::Module(0), ModuleNode::Module(1), ModuleNode::V(), ModuleNode::Module(2), ModuleNode::V(),
+            ModuleNode::Module(3), ModuleNode::Module(4), ModuleNode::H(), ModuleNode::V()];
+        assert_eq!(joined, expected);
+    }
+
+    #[test]
+    fn test_slice_floorplan() {
+        let plan: Floorplan = vec![(0, 0, Rectangle::new(2, 1)), (0, 1, Rectangle::new(1, 1)), (1, 1, Rectangle::new(1, 1))];
+        let mut pe = PolishExpression::new(plan.iter().map(|&(_, _, rect)| rect).collect(), Vec::new(), 1.0);
+        assert_eq!(pe.set_solution_floorplan(&plan), Ok(()));
+        let expected = vec![ModuleNode::Module(0), ModuleNode::Module(1), ModuleNode::Module(2), ModuleNode::V(), ModuleNode::H()];
+        assert_eq!(pe.copy_solution(), expected);
+        // pinwheel around module 4
+        let plan: Floorplan = vec![(0, 0, Rectangle::new(2, 1)), (2, 0, Rectangle::new(1, 2)), (1, 2, Rectangle::new(2, 1)),
+            (0, 1, Rectangle::new(1, 2)), (1, 1, Rectangle::new(1, 1))];
+        assert!(PolishExpression::slice(&plan, (0..5).collect()).is_err());
     }
 }
@@ -1,8 +1,17 @@
 use crate::definitions::*;
 use crate::floorplan_common::*;
+use crate::crossover::*;
+use crate::constraints::SymmetryGroup;
 use rand::prelude::*;
 
+// stop moving modules to a symmetric position after this many packings
+const MAX_SYMMETRY_ROUNDS: usize = 100;
+
 pub type SequencePairSolution = (Vec<Int>, Vec<Int>, Vec<Rectangle>);
+// (a, b, gap) for x(a) >= x(b) + gap
+type Spacing = (Int, Int, Int);
+// (group, l, offset) for axis >= 2 x(l) + offset
+type AxisBound = (usize, Int, Int);
 
 #[derive(Debug)]
 pub enum SPMoveType {
@@ -34,6 +43,44 @@ impl SPMoveType {
     }
 }
 
+// order of n nodes respecting the relation before, among the free nodes the one with the smallest key is taken
+// nodes on a cycle of the relation are appended by their key
+fn topological_order(n: usize, before: impl Fn(Int, Int) -> bool, key: impl Fn(Int) -> f64) -> Vec<Int> {
+    let mut by_key: Vec<Int> = (0..n).collect();
+    by_key.sort_by(|&a, &b| key(a).total_cmp(&key(b)));
+    let mut rank: Vec<usize> = vec![0; n];
+    for (r, &i) in by_key.iter().enumerate() {
+        rank[i] = r;
+    }
+    let mut successors: Vec<Vec<Int>> = vec![Vec::new(); n];
+    let mut in_degree: Vec<usize> = vec![0; n];
+    for (a, next) in successors.iter_mut().enumerate() {
+        for b in (0..n).filter(|&b| b != a && before(a, b)) {
+            next.push(b);
+            in_degree[b] += 1;
+        }
+    }
+    let mut free: std::collections::BinaryHeap<std::cmp::Reverse<usize>> = (0..n)
+        .filter(|&i| in_degree[i] == 0)
+        .map(|i| std::cmp::Reverse(rank[i]))
+        .collect();
+    let mut order: Vec<Int> = Vec::with_capacity(n);
+    let mut done: Vec<bool> = vec![false; n];
+    while let Some(std::cmp::Reverse(r)) = free.pop() {
+        let a = by_key[r];
+        order.push(a);
+        done[a] = true;
+        for &b in successors[a].iter() {
+            in_degree[b] -= 1;
+            if in_degree[b] == 0 {
+                free.push(std::cmp::Reverse(rank[b]));
+            }
+        }
+    }
+    order.extend(by_key.into_iter().filter(|&i| !done[i]));
+    order
+}
+
 #[derive(Debug, Default)]
 pub struct SequencePair {
     modules: Vec<Rectangle>,
@@ -44,6 +91,15 @@ pub struct SequencePair {
     index_x: Vec<usize>, // index of number i in x_sequence
     index_y: Vec<usize>, // index of number i in y_sequence
     placement: Floorplan,
+    fixed: Vec<Option<(Int, Int, Rectangle)>>, // position and shape of pre-placed modules
+    fixed_ids: Vec<usize>,
+    symmetry_groups: Vec<SymmetryGroup>,
+    counterpart: Vec<Option<(usize, usize)>>, // symmetry group and symmetric counterpart of each module
+    min_x: Vec<Int>, // lower bounds of the coordinates used by the symmetric packing
+    min_y: Vec<Int>,
+    asymmetric_packings: usize, // packings that left a symmetry group asymmetric
+    backup: SequencePairSolution, // state before the last move if there are symmetry groups
+    crossover_type: CrossoverType,
     
     cost_function: CostFunction,
     current_cost: f64,
@@ -68,6 +124,8 @@ impl SequencePair {
         sp.len_vec = (0..n).collect();
         sp.index_x  = (0..n).collect();
         sp.index_y  = (0..n).collect();
+        sp.min_x = vec![0; n];
+        sp.min_y = vec![0; n];
 
         // update cost parameter
         sp.cost_function = CostFunction::new(alpha, 1.0, 1.0);
@@ -77,41 +135,169 @@ impl SequencePair {
         sp
     }
 
-    pub fn set_solution_recursive_bisection(&mut self, order: &Vec<Int>) {
-        let (x_sequence, y_sequence) = self.recursive_bisection(&order, 0, self.modules.len(), true);
+    /// pre-placed modules keep their position and their current orientation, the other modules are packed around them,
+    /// has to be called before an initial solution is set
+    pub fn set_fixed_modules(&mut self, fixed: &[(usize, Int, Int)]) {
+        self.fixed = vec![None; self.modules.len()];
+        self.fixed_ids = fixed.iter().map(|&(id, _, _)| id).collect();
+        for &(id, x, y) in fixed.iter() {
+            self.fixed[id] = Some((x, y, self.modules[id]));
+        }
+        self.update();
+    }
+
+    // a module m that precedes a fixed module in the constraint graph and would overlap it is pushed past its far edge,
+    // so its successors are packed behind the fixed module as well
+    // x-coordinates are known in the vertical pass, only modules overlapping the fixed one in x are pushed there
+    // the coordinate l only grows, so each fixed module pushes at most once
+    fn push_past_fixed(&self, m: usize, mut l: Int, length: Int, horizontal: bool) -> Int {
+        loop {
+            let mut pushed = false;
+            for &f in self.fixed_ids.iter() {
+                let (x, y, rect) = self.fixed[f].unwrap();
+                let (start, end, precedes) = if horizontal {
+                    (x, x + rect.width, self.index_x[m] < self.index_x[f] && self.index_y[m] < self.index_y[f])
+                }
+                else {
+                    let x_m = self.placement[m].0;
+                    let overlaps_x = x_m < x + rect.width && x < x_m + self.modules[m].width;
+                    (y, y + rect.height, overlaps_x && self.index_x[m] > self.index_x[f] && self.index_y[m] < self.index_y[f])
+                };
+                if precedes && l < end && l + length > start {
+                    l = end;
+                    pushed = true;
+                }
+            }
+            if !pushed {
+                return l;
+            }
+        }
+    }
+
+    /// number of packings so far in which a symmetry group could not be placed symmetrically
+    pub fn get_asymmetric_packings(&self) -> usize {
+        self.asymmetric_packings
+    }
+
+    fn is_fixed(&self, id: usize) -> bool {
+        matches!(self.fixed.get(id), Some(Some(_)))
+    }
+
+    /// symmetry groups of modules placed symmetrically to a vertical axis (Balasa, Lampaert)
+    /// the y sequence is repaired after each move such that the sequence pair stays symmetric-feasible
+    pub fn set_symmetry_groups(&mut self, groups: &[SymmetryGroup]) {
+        self.symmetry_groups = groups.to_vec();
+        self.counterpart = vec![None; self.modules.len()];
+        for (g, group) in self.symmetry_groups.iter().enumerate() {
+            for (a, b) in group.counterparts() {
+                self.counterpart[a] = Some((g, b));
+            }
+            for &(a, b) in group.pairs.iter() {
+                let (rect_a, rect_b) = (self.modules[a], self.modules[b]);
+                if rect_a != rect_b && rect_a != rect_b.transpose() {
+                    panic!("symmetric modules {} and {} have different shapes", a, b);
+                }
+            }
+        }
+        self.repair_symmetry();
+        self.update();
+    }
+
+    // symmetric-feasible condition: for modules x, y of a group
+    // x before y in the x sequence <=> sym(y) before sym(x) in the y sequence
+    // the group members in the y sequence are reordered accordingly, both modules of a pair get the same orientation
+    fn repair_symmetry(&mut self) {
+        let n = self.modules.len();
+        for g in 0..self.symmetry_groups.len() {
+            let in_group = |id: &Int| matches!(self.counterpart[*id], Some((h, _)) if h == g);
+            let order: Vec<Int> = self.x_sequence.iter().filter(|id| in_group(id)).copied().collect();
+            let positions: Vec<usize> = (0..n).filter(|&pos| in_group(&self.y_sequence[pos])).collect();
+            for (&pos, &id) in positions.iter().zip(order.iter().rev()) {
+                self.y_sequence[pos] = self.counterpart[id].unwrap().1;
+            }
+            for &(a, b) in self.symmetry_groups[g].pairs.iter() {
+                self.modules[b] = self.modules[a];
+            }
+        }
+        for (pos, id) in self.y_sequence.iter().enumerate() {
+            self.index_y[*id] = pos;
+        }
+    }
+
+    pub fn set_crossover_type(&mut self, crossover_type: CrossoverType) {
+        self.crossover_type = crossover_type;
+    }
+
+    /// cuts are the split positions of the parts with more than two modules in preorder
+    /// the orientation of each cut is chosen by the shape of the region, starting with a square of the module area
+    pub fn set_solution_recursive_bisection(&mut self, order: &Vec<Int>, cuts: &[usize]) {
+        let side = (self.modules.iter().map(|rect| rect.area()).sum::<Int>() as f64).sqrt();
+        let mut rect = self.modules.clone();
+        let (x_sequence, y_sequence) = self.recursive_bisection(order, &mut cuts.iter(), 0, self.modules.len(), (side, side), &mut rect);
+        self.set_solution((x_sequence, y_sequence, rect));
+        self.update_cost_function();
+    }
+
+    /// sequence pair of module centers, e.g. from a global placement:
+    /// the x sequence is sorted by x - y and the y sequence by x + y,
+    /// a module left of or below another module keeps this relation if their centers do not overlap diagonally
+    pub fn set_solution_positions(&mut self, positions: &[(f64, f64)]) {
+        let mut x_sequence: Vec<Int> = (0..self.modules.len()).collect();
+        let mut y_sequence = x_sequence.clone();
+        x_sequence.sort_by(|&a, &b| (positions[a].0 - positions[a].1).total_cmp(&(positions[b].0 - positions[b].1)));
+        y_sequence.sort_by(|&a, &b| (positions[a].0 + positions[a].1).total_cmp(&(positions[b].0 + positions[b].1)));
         self.set_solution((x_sequence, y_sequence, self.modules.clone()));
+        self.update_cost_function();
+    }
+
+    /// sequence pair of a floorplan, e.g. the floorplan of another representation:
+    /// a before b in the x sequence if a is left of or above b, in the y sequence if a is left of or below b,
+    /// a pair that is separated both horizontally and vertically may take either relation
+    /// if the floorplan has no overlaps, each module of the same area keeps its shape and is packed at most as far right and up,
+    /// so the packing is at most as large as the floorplan,
+    /// overlapping modules are not related and only ordered by the position of their centers
+    pub fn set_solution_floorplan(&mut self, plan: &Floorplan) {
+        let left = |a: Int, b: Int| plan[a].0 + plan[a].2.width <= plan[b].0;
+        let below = |a: Int, b: Int| plan[a].1 + plan[a].2.height <= plan[b].1;
+        let center = |a: Int| (plan[a].0 as f64 + plan[a].2.width as f64 / 2.0, plan[a].1 as f64 + plan[a].2.height as f64 / 2.0);
+        let x_sequence = topological_order(plan.len(),
+            |a, b| (left(a, b) && !below(a, b)) || (below(b, a) && !left(b, a)),
+            |a| center(a).0 - center(a).1);
+        let y_sequence = topological_order(plan.len(),
+            |a, b| (left(a, b) && !below(b, a)) || (below(a, b) && !left(b, a)),
+            |a| center(a).0 + center(a).1);
+        // modules take the shape of the floorplan, e.g. the realization of a soft module of the same area
+        let rect: Vec<Rectangle> = self.modules.iter().zip(plan.iter())
+            .map(|(&module, &(_, _, rect))| if rect.area() == module.area() { rect } else { module })
+            .collect();
+        self.set_solution((x_sequence, y_sequence, rect));
+        self.update_cost_function();
     }
 
     // intervall [l, r)
-    fn recursive_bisection(&self, order: &Vec<Int>, left: usize, right: usize, split_horizontal: bool) -> (Vec<Int>, Vec<Int>) {
+    // modules are rotated to the orientation of their region
+    fn recursive_bisection(&self, order: &Vec<Int>, cuts: &mut std::slice::Iter<usize>, left: usize, right: usize, region: (f64, f64), rect: &mut Vec<Rectangle>) -> (Vec<Int>, Vec<Int>) {
         if right - left == 1 {
             let i = order[left];
+            if (region.0 >= region.1) != (rect[i].width >= rect[i].height) && !self.is_fixed(i) {
+                rect[i] = rect[i].transpose();
+            }
             return (vec![i], vec![i]);
         }
-        else if right - left == 2 {
-            let i = order[left];
-            let j = order[left + 1];
-            if split_horizontal {
-                return (vec![i, j], vec![i, j]);
-            }
-            else {
-                return (vec![j, i], vec![i, j]);
-            }
+        let mid = if right - left == 2 { left + 1 } else { *cuts.next().unwrap() };
+        let area = |l: usize, r: usize| order[l..r].iter().map(|&i| self.modules[i].area()).sum::<Int>() as f64;
+        let (side_by_side, region1, region2) = split_region(region, area(left, mid) / area(left, right));
+        let (mut x1, mut y1) = self.recursive_bisection(order, cuts, left, mid, region1, rect);
+        let (mut x2, mut y2) = self.recursive_bisection(order, cuts, mid, right, region2, rect);
+        if side_by_side {
+            x1.append(&mut x2);
+            y1.append(&mut y2);
+            (x1, y1)
         }
         else {
-            let mid = (left + right).div_ceil(2);
-            let (mut x1, mut y1) = self.recursive_bisection(order, left, mid, split_horizontal ^ true);
-            let (mut x2, mut y2) = self.recursive_bisection(order, mid, right, split_horizontal ^ true);
-            if split_horizontal {
-                x1.append(&mut x2);
-                y1.append(&mut y2);
-                return (x1, y1);
-            }
-            else {
-                x2.append(&mut x1);
-                y1.append(&mut y2);
-                return (x2, y1);
-            }
+            x2.append(&mut x1);
+            y1.append(&mut y2);
+            (x2, y1)
         }
     }
 
@@ -125,24 +311,40 @@ impl SequencePair {
     pub fn update_cost_function(&mut self) {
         // compute averages for cost function
         let repetitions = 3 * self.modules.len();
-        let (avg_wirelength,avg_area) = CostFunction::compute_mean_parameters(self, repetitions);
-        self.cost_function = CostFunction::new(self.cost_function.alpha, avg_wirelength, avg_area);
-        self.current_cost = self.cost_function.get_cost(self.current_area, self.current_wire);
+        let samples = sample_perturbations(self, repetitions, self.cost_function.has_terms());
+        self.cost_function.update_averages(&samples);
+        // perturbations are reversed without update
+        self.update();
+    }
+
+    /// registers additional weighted cost terms and re-estimates their averages
+    pub fn set_cost_terms(&mut self, terms: Vec<WeightedCostTerm>) {
+        self.cost_function.set_terms(terms);
+        self.update_cost_function();
     }
 
     pub fn compute_cost(&mut self) -> f64 {
-        self.cost_function.get_cost(self.current_area, self.current_wire)
+        self.cost_function.get_cost(self.current_area, self.current_wire, &self.placement)
     }
     
-    pub fn compute_floorplan(&mut self) {
+    // longest paths in the horizontal constraint graph with lower bounds min_x
+    // and spacings (a, b, gap) for x(a) >= x(b) + gap, where b precedes a in the x sequence
+    fn compute_x_coordinates(&mut self, spacings: &[Spacing]) {
         let n = self.placement.len();
-
-        // x-coordinates
         self.len_vec.fill(0);
         for i in 0..n {
             let x_id = self.x_sequence[i];
             let pos_y = self.index_y[x_id];
-            let l = self.len_vec[pos_y];
+            let l = match self.fixed.get(x_id) {
+                Some(Some((x, _, _))) => *x,
+                _ => {
+                    let spaced = spacings.iter()
+                        .filter(|&&(a, _, _)| a == x_id)
+                        .map(|&(_, b, gap)| self.placement[b].0 + gap)
+                        .fold(self.len_vec[pos_y].max(self.min_x[x_id]), Int::max);
+                    self.push_past_fixed(x_id, spaced, self.modules[x_id].width, true)
+                }
+            };
             self.placement[x_id].0 = l;
             let t = l + self.modules[x_id].width;
             for j in pos_y..n {
@@ -154,13 +356,19 @@ impl SequencePair {
                 }
             }
         }
+    }
 
-        // y-coordinates
+    // longest paths in the vertical constraint graph with lower bounds min_y
+    fn compute_y_coordinates(&mut self) {
+        let n = self.placement.len();
         self.len_vec.fill(0);
         for i in 0..n {
             let y_id = self.y_sequence[i];
             let pos_x = n - 1 - self.index_x[y_id]; // reversing x sequence -> lca(x^R, y)
-            let l = self.len_vec[pos_x];
+            let l = match self.fixed.get(y_id) {
+                Some(Some((_, y, _))) => *y,
+                _ => self.push_past_fixed(y_id, self.len_vec[pos_x].max(self.min_y[y_id]), self.modules[y_id].height, false),
+            };
             self.placement[y_id].1 = l;
             let t = l + self.modules[y_id].height;
             for j in pos_x..n {
@@ -172,13 +380,156 @@ impl SequencePair {
                 }
             }
         }
-        
+    }
+
+    // longest paths from source in the horizontal constraint graph, None for modules that are not right of it
+    fn horizontal_distances(&self, source: Int) -> Vec<Option<Int>> {
+        let n = self.modules.len();
+        let mut dist: Vec<Option<Int>> = vec![None; n];
+        let mut len_vec: Vec<Option<Int>> = vec![None; n];
+        dist[source] = Some(0);
+        for &id in self.x_sequence[self.index_x[source]..].iter() {
+            let pos_y = self.index_y[id];
+            if id != source {
+                dist[id] = len_vec[pos_y];
+            }
+            let Some(d) = dist[id] else { continue };
+            let t = Some(d + self.modules[id].width);
+            for len in len_vec[pos_y..].iter_mut() {
+                if t > *len {
+                    *len = t;
+                }
+                else {
+                    break;
+                }
+            }
+        }
+        dist
+    }
+
+    // in a symmetric-feasible sequence pair both modules of a pair are side by side, left module first in the x sequence,
+    // and no right module is a predecessor of a left or self-symmetric module,
+    // so only the right modules depend on the axis and the constraints between them are mirrored to the left modules:
+    // a right module left of another right module keeps at least their distance in the constraint graph,
+    // a self-symmetric module left of a right module bounds the axis from below
+    // returns spacings (a, b, gap) for x(a) >= x(b) + gap and axis bounds (group, l, offset) for axis >= 2 x(l) + offset
+    fn symmetry_bounds(&self) -> (Vec<Spacing>, Vec<AxisBound>) {
+        let mut spacings: Vec<Spacing> = Vec::new();
+        let mut axis_bounds: Vec<AxisBound> = Vec::new();
+        let width = |i: usize| self.modules[i].width;
+        for (g, group) in self.symmetry_groups.iter().enumerate() {
+            let ordered: Vec<(Int, Int)> = group.pairs.iter()
+                .map(|&(a, b)| if self.index_x[a] < self.index_x[b] { (a, b) } else { (b, a) })
+                .collect();
+            for &(l_p, r_p) in ordered.iter() {
+                let dist = self.horizontal_distances(r_p);
+                for &(l, r) in ordered.iter().filter(|&&(_, r)| r != r_p) {
+                    if let Some(d) = dist[r] {
+                        spacings.push((l_p, l, width(l) + d - width(l_p)));
+                    }
+                }
+            }
+            for &s in group.self_symmetric.iter() {
+                let dist = self.horizontal_distances(s);
+                for &(l, r) in ordered.iter() {
+                    if let Some(d) = dist[r] {
+                        axis_bounds.push((g, l, 2 * (width(l) + d) - width(s)));
+                    }
+                }
+            }
+        }
+        (spacings, axis_bounds)
+    }
+
+    // left modules are spread by the mirrored spacings, the axis is the maximum over the group and
+    // right and self-symmetric modules are raised to their mirrored position,
+    // then both modules of a pair are raised to the same height
+    // coordinates of centers and axis are doubled to stay integral
+    // a single group without fixed modules is symmetric after two horizontal packings,
+    // further rounds are only needed if groups or fixed modules push each other
+    // returns the number of horizontal and vertical packings or None if the rounds do not converge,
+    // e.g. for self-symmetric modules of different width parity
+    fn compute_symmetric_coordinates(&mut self) -> Option<(usize, usize)> {
+        self.min_x.fill(0);
+        self.min_y.fill(0);
+        let (spacings, axis_bounds) = self.symmetry_bounds();
+        // x-coordinates first, the vertical pass needs them for fixed modules
+        let mut x_rounds = None;
+        for round in 1..=MAX_SYMMETRY_ROUNDS {
+            self.compute_x_coordinates(&spacings);
+            let x = |i: usize| self.placement[i].0;
+            let width = |i: usize| self.modules[i].width;
+            let mut targets: Vec<(usize, Int)> = Vec::new();
+            for (g, group) in self.symmetry_groups.iter().enumerate() {
+                let ordered = |(a, b): (usize, usize)| if self.index_x[a] < self.index_x[b] { (a, b) } else { (b, a) };
+                let mut axis = group.pairs.iter().map(|&(a, b)| x(a) + x(b) + width(a))
+                    .chain(group.self_symmetric.iter().map(|&s| 2 * x(s) + width(s)))
+                    .chain(axis_bounds.iter().filter(|b| b.0 == g).map(|&(_, l, offset)| 2 * x(l) + offset))
+                    .max()
+                    .unwrap_or(0);
+                if group.self_symmetric.first().is_some_and(|&s| (axis + width(s)) % 2 == 1) {
+                    axis += 1;
+                }
+                for &pair in group.pairs.iter() {
+                    let (l, r) = ordered(pair);
+                    targets.push((r, axis - width(l) - x(l)));
+                }
+                for &s in group.self_symmetric.iter() {
+                    targets.push((s, (axis - width(s)) / 2));
+                }
+            }
+            let mut raised = false;
+            for (i, target) in targets {
+                if self.placement[i].0 < target {
+                    self.min_x[i] = target;
+                    raised = true;
+                }
+            }
+            if !raised {
+                x_rounds = Some(round);
+                break;
+            }
+        }
+        for round in 1..=MAX_SYMMETRY_ROUNDS {
+            self.compute_y_coordinates();
+            let mut changed = false;
+            for group in self.symmetry_groups.iter() {
+                for &(a, b) in group.pairs.iter() {
+                    let (y_a, y_b) = (self.placement[a].1, self.placement[b].1);
+                    if y_a != y_b {
+                        self.min_y[a] = y_a.max(y_b);
+                        self.min_y[b] = y_a.max(y_b);
+                        changed = true;
+                    }
+                }
+            }
+            if !changed {
+                return x_rounds.map(|x_rounds| (x_rounds, round));
+            }
+        }
+        None
+    }
+
+    pub fn compute_floorplan(&mut self) {
+        let n = self.placement.len();
+        let converged = if self.symmetry_groups.is_empty() {
+            self.compute_x_coordinates(&[]);
+            self.compute_y_coordinates();
+            true
+        }
+        else {
+            self.compute_symmetric_coordinates().is_some()
+        };
+
         // write rotation of rectangles into floorplan
         for i in 0..n {
             let width = self.modules[i].width;
             let height = self.modules[i].height;
             self.placement[i].2 = Rectangle::new(width, height);
         }
+        if !converged || self.symmetry_groups.iter().any(|group| group.get_asymmetry(&self.placement) > 0) {
+            self.asymmetric_packings += 1;
+        }
 
         // compute bounding box
         self.bounding_box.width = self.placement.iter().map(|(x, _, r)| *x + (*r).width).max().unwrap();
@@ -213,7 +564,17 @@ impl Mutation<SPMoveType> for SequencePair {
         let n = self.modules.len();
         let r = random!(4);
         let move_type: SPMoveType = match r {
-            0 => SPMoveType::RotateModule(random!(n)),
+            0 => {
+                let a = random!(n);
+                if self.is_fixed(a) {
+                    // orientation of fixed modules is given
+                    let (a, b) = two_random!(n);
+                    SPMoveType::SwapLeftSide(a, b)
+                }
+                else {
+                    SPMoveType::RotateModule(a)
+                }
+            },
             1 => {
                 let (a, b) = two_random!(n);
                 SPMoveType::SwapLeftSide(a, b)
@@ -231,7 +592,28 @@ impl Mutation<SPMoveType> for SequencePair {
         move_type
     }
     fn apply_move(&mut self, _move: &SPMoveType, update: bool) {
-        _move.apply(self);
+        if self.symmetry_groups.is_empty() {
+            _move.apply(self);
+        }
+        else if update {
+            // the repair is not reversible, the previous state is restored from the backup
+            self.backup.0.clone_from(&self.x_sequence);
+            self.backup.1.clone_from(&self.y_sequence);
+            self.backup.2.clone_from(&self.modules);
+            _move.apply(self);
+            self.repair_symmetry();
+        }
+        else {
+            std::mem::swap(&mut self.x_sequence, &mut self.backup.0);
+            std::mem::swap(&mut self.y_sequence, &mut self.backup.1);
+            std::mem::swap(&mut self.modules, &mut self.backup.2);
+            for (pos, id) in self.x_sequence.iter().enumerate() {
+                self.index_x[*id] = pos;
+            }
+            for (pos, id) in self.y_sequence.iter().enumerate() {
+                self.index_y[*id] = pos;
+            }
+        }
         if update {
             self.update()
         }
@@ -274,8 +656,14 @@ impl Solution<SequencePairSolution> for SequencePair {
         for (pos, id) in self.y_sequence.iter().enumerate() {
             self.index_y[*id] = pos;
         }
-        self.update();
-        self.update_cost_function()
+        // fixed modules keep their orientation, e.g. in a projected or converted solution
+        for &id in self.fixed_ids.iter() {
+            self.modules[id] = self.fixed[id].unwrap().2;
+        }
+        if !self.symmetry_groups.is_empty() {
+            self.repair_symmetry();
+        }
+        self.update()
     }
 }
 
@@ -285,53 +673,162 @@ impl RandomSolution<SequencePairSolution> for SequencePair {
         let mut y_sequence: Vec<usize> = x_sequence.clone();
         let mut rect = self.modules.clone();
         for i in 0..rect.len() {
-            if thread_rng().gen_bool(0.5) {
+            if !self.is_fixed(i) && thread_rng().gen_bool(0.5) {
                 rect[i] = rect[i].transpose();
             }
         }
         x_sequence.shuffle(&mut thread_rng());
         y_sequence.shuffle(&mut thread_rng());
-        rect.shuffle(&mut thread_rng());
         (x_sequence, y_sequence, rect)
     }
 }
 
 impl Crossover<SequencePairSolution> for SequencePair {
+    // the same operator and cut are applied to both sequences,
+    // each module keeps the orientation of the parent it was inherited from in the x sequence
     fn crossover(&self, a: &SequencePairSolution, b: &SequencePairSolution) -> SequencePairSolution {
-        
-        let n = self.modules.len();
-        let n_half = n.div_ceil(2);
-        let mut selected = vec![false; n_half];
-        let mut n2 = vec![true; n - n_half];
-        selected.append(&mut n2);
-
-        let mut x_sequence: Vec<usize> = Vec::new();
-        let mut y_sequence: Vec<usize> = Vec::new();
-        let mut rect: Vec<Rectangle> = Vec::new();
-        x_sequence.reserve_exact(n);
-        y_sequence.reserve_exact(n);
-        rect.reserve_exact(n);
+        let crossover_type = self.crossover_type.choose();
+        let cut = random_cut(a.0.len());
+        let (x_sequence, from_a) = crossover_type.apply(&a.0, &b.0, cut);
+        let (y_sequence, _) = crossover_type.apply(&a.1, &b.1, cut);
+        let rect: Vec<Rectangle> = (0..from_a.len()).map(|i| if from_a[i] { a.2[i] } else { b.2[i] }).collect();
+        (x_sequence, y_sequence, rect)
+    }
+}
 
-        for i in 0..n {
-            if selected[a.0[i]] {
-                x_sequence.push(a.0[i]);
-            }
-            if selected[a.1[i]] {
-                y_sequence.push(a.1[i]);
-            }
-            if selected[i] {
-                rect.push(a.2[i]);
-            }
-            if !selected[b.0[i]] {
-                x_sequence.push(b.0[i]);
-            }
-            if !selected[b.1[i]] {
-                y_sequence.push(b.1[i]);
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_set_solution_floorplan() {
+        // pinwheel around module 4, a non-slicing floorplan
+        let plan: Floorplan = vec![(0, 0, Rectangle::new(2, 1)), (2, 0, Rectangle::new(1, 2)), (1, 2, Rectangle::new(2, 1)),
+            (0, 1, Rectangle::new(1, 2)), (1, 1, Rectangle::new(1, 1))];
+        let modules: Vec<Rectangle> = plan.iter().map(|&(_, _, rect)| rect).collect();
+        let mut sp = SequencePair::new(modules, vec![Net::new(vec![0, 2], 0)], 1.0);
+        sp.set_solution_floorplan(&plan);
+        assert_eq!(sp.get_floorplan(), plan);
+        assert_eq!(sp.get_floor_area(), 9.0);
+
+        // soft module 0 realized with another shape of the same area
+        let plan: Floorplan = vec![(0, 0, Rectangle::new(4, 1)), (0, 1, Rectangle::new(4, 1))];
+        let mut sp = SequencePair::new(vec![Rectangle::new(2, 2), Rectangle::new(4, 1)], Vec::new(), 1.0);
+        sp.set_solution_floorplan(&plan);
+        assert_eq!(sp.get_floorplan(), plan);
+
+        // packings spread apart with dead area, no module is packed right of or above its position
+        let mut rng = StdRng::seed_from_u64(0);
+        let modules: Vec<Rectangle> = (0..15).map(|_| Rectangle::new(rng.gen_range(1..10), rng.gen_range(1..10))).collect();
+        let mut sp = SequencePair::new(modules, Vec::new(), 1.0);
+        for _ in 0..100 {
+            sp.set_solution(sp.random_solution());
+            let mut plan: Floorplan = sp.get_floorplan();
+            for (x, y, rect) in plan.iter_mut() {
+                let shift = rect.width.min(rect.height);
+                *x = 2 * *x + rng.gen_range(0..=shift);
+                *y = 2 * *y + rng.gen_range(0..=shift);
             }
-            if !selected[i] {
-                rect.push(a.2[i]);
+            assert!(crate::compaction::find_overlaps(&plan).is_empty());
+            sp.set_solution_floorplan(&plan);
+            for (&(x, y, rect), &(plan_x, plan_y, plan_rect)) in sp.get_floorplan().iter().zip(plan.iter()) {
+                assert!(x <= plan_x && y <= plan_y && rect == plan_rect);
             }
         }
-        (x_sequence, y_sequence, rect)
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_fixed_modules() {
+        // 1 precedes the fixed module 0 in both sequences, 2 follows all, 3 is below 0 and 1
+        let modules = vec![Rectangle::new(3, 2), Rectangle::new(3, 1), Rectangle::new(1, 1), Rectangle::new(4, 2)];
+        let mut sp = SequencePair::new(modules.clone(), Vec::new(), 1.0);
+        sp.set_fixed_modules(&[(0, 2, 1)]);
+        let mut rect = modules.clone();
+        rect[0] = rect[0].transpose();
+        sp.set_solution((vec![1, 0, 3, 2], vec![3, 1, 0, 2], rect));
+        // 1 and 3 would overlap 0 and are pushed past its far edge, so 1 ends up right of 0 and 3 above it, 2 is packed behind 1
+        assert_eq!(sp.get_floorplan(), vec![
+            (2, 1, Rectangle::new(3, 2)),
+            (5, 5, Rectangle::new(3, 1)),
+            (8, 0, Rectangle::new(1, 1)),
+            (0, 3, Rectangle::new(4, 2)),
+        ]);
+
+        let mut rng = StdRng::seed_from_u64(0);
+        let modules: Vec<Rectangle> = (0..15).map(|_| Rectangle::new(rng.gen_range(1..10), rng.gen_range(1..10))).collect();
+        let mut sp = SequencePair::new(modules, Vec::new(), 1.0);
+        sp.set_fixed_modules(&[(0, 5, 5), (1, 20, 0), (2, 0, 20)]);
+        for _ in 0..200 {
+            let _move = sp.get_random_move();
+            sp.apply_move(&_move, true);
+            assert!(crate::compaction::find_overlaps(&sp.get_floorplan()).is_empty());
+            assert_eq!((sp.placement[0].0, sp.placement[0].1), (5, 5));
+        }
+    }
+
+    #[test]
+    fn test_solution_positions() {
+        // centers of a 2 x 2 grid are packed to the grid
+        let mut sp = SequencePair::new(vec![Rectangle::new(1, 1); 4], Vec::new(), 1.0);
+        sp.set_solution_positions(&[(0.5, 0.5), (1.5, 0.5), (0.5, 1.5), (1.5, 1.5)]);
+        let positions: Vec<(Int, Int)> = sp.get_floorplan().iter().map(|&(x, y, _)| (x, y)).collect();
+        assert_eq!(positions, vec![(0, 0), (1, 0), (0, 1), (1, 1)]);
+    }
+
+    #[test]
+    fn test_symmetric_packing() {
+        // pairs (0, 1) and (2, 3), 4 is self-symmetric, 5 and 6 are not in the group
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut modules: Vec<Rectangle> = (0..7).map(|_| Rectangle::new(rng.gen_range(1..10), rng.gen_range(1..10))).collect();
+        modules[1] = modules[0];
+        modules[3] = modules[2].transpose();
+        let group = SymmetryGroup { pairs: vec![(0, 1), (2, 3)], self_symmetric: vec![4] };
+        let mut sp = SequencePair::new(modules, Vec::new(), 1.0);
+        sp.set_symmetry_groups(std::slice::from_ref(&group));
+        assert_eq!(group.get_asymmetry(&sp.get_floorplan()), 0);
+        for _ in 0..500 {
+            let _move = sp.get_random_move();
+            sp.apply_move(&_move, true);
+            assert_eq!(group.get_asymmetry(&sp.get_floorplan()), 0);
+            assert!(crate::compaction::find_overlaps(&sp.get_floorplan()).is_empty());
+        }
+        assert_eq!(sp.get_asymmetric_packings(), 0);
+
+        // pair (2, 3) encloses pair (0, 1), module 4 lies between the right modules 1 and 2,
+        // so the left modules 3 and 0 keep the same distance
+        let modules = vec![Rectangle::new(2, 1), Rectangle::new(2, 1), Rectangle::new(1, 1), Rectangle::new(1, 1), Rectangle::new(3, 1)];
+        let group = SymmetryGroup { pairs: vec![(0, 1), (2, 3)], self_symmetric: Vec::new() };
+        let mut sp = SequencePair::new(modules.clone(), Vec::new(), 1.0);
+        sp.set_symmetry_groups(std::slice::from_ref(&group));
+        sp.set_solution((vec![3, 0, 1, 4, 2], vec![3, 0, 1, 4, 2], modules));
+        let x: Vec<Int> = sp.get_floorplan().iter().map(|&(x, _, _)| x).collect();
+        assert_eq!(x, vec![4, 6, 11, 0, 8]);
+        assert_eq!(group.get_asymmetry(&sp.get_floorplan()), 0);
+    }
+
+    #[test]
+    fn test_symmetric_rounds() {
+        // 4 pairs and 2 self-symmetric modules of even width and height among 20 modules
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut modules: Vec<Rectangle> = (0..20).map(|_| Rectangle::new(rng.gen_range(1..10), rng.gen_range(1..10))).collect();
+        for i in 0..4 {
+            modules[2 * i + 1] = modules[2 * i];
+        }
+        modules[8] = Rectangle::new(4, 2);
+        modules[9] = Rectangle::new(6, 4);
+        let group = SymmetryGroup { pairs: (0..4).map(|i| (2 * i, 2 * i + 1)).collect(), self_symmetric: vec![8, 9] };
+        let mut sp = SequencePair::new(modules, Vec::new(), 1.0);
+        sp.set_symmetry_groups(std::slice::from_ref(&group));
+        // the mirrored spacings and axis bounds place a single group after two horizontal packings,
+        // each vertical round lifts at least the lowest pair that is not yet level
+        for _ in 0..1000 {
+            let _move = sp.get_random_move();
+            sp.apply_move(&_move, true);
+            let (x_rounds, y_rounds) = sp.compute_symmetric_coordinates().unwrap();
+            assert!(x_rounds <= 2);
+            assert!(y_rounds <= group.pairs.len() + 1);
+            assert_eq!(group.get_asymmetry(&sp.get_floorplan()), 0);
+        }
+        assert_eq!(sp.get_asymmetric_packings(), 0);
+    }
+}
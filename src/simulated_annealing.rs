@@ -16,6 +16,7 @@ pub struct SimulatedAnnealing {
     iterations: usize,
     initial_temperature: f64,
     decay: f64,
+    verbose: bool,
 }
 
 impl SimulatedAnnealing {
@@ -51,9 +52,15 @@ impl SimulatedAnnealing {
             iterations: iterations,
             initial_temperature: initial_temperature,
             decay,
+            verbose: true,
         }
     }
 
+    // disables logging, e.g. for short runs as local search
+    pub fn set_verbose(&mut self, verbose: bool) {
+        self.verbose = verbose;
+    }
+
     pub fn run<T: Mutation<Move> + Cost + Solution<S>, Move, S: Clone + Debug>(&self, instance: &mut T) {
         let mut temperature: f64 = self.initial_temperature;
         let mut rng: ThreadRng = rand::thread_rng();
@@ -84,7 +91,7 @@ impl SimulatedAnnealing {
             }
             
             // logging
-            if i % (self.iterations / 10) == 0 {
+            if self.verbose && i % (self.iterations / 10).max(1) == 0 {
                 eprintln!("it: {}, T {:.2}, cost {:.2}, delta {:.2}, best {:.2}", i, temperature, current_cost, delta, best_cost);
             }
             // sample SA cost
@@ -93,7 +100,9 @@ impl SimulatedAnnealing {
             // }
             temperature *= self.decay;
         }
-        eprintln!("best cost {:?}", best_cost);
+        if self.verbose {
+            eprintln!("best cost {:?}", best_cost);
+        }
         instance.set_solution(best_solution);
     }
 
@@ -40,6 +40,41 @@ impl Default for ModuleShape {
     }
 }
 
+/// selects which shape of the root shape function is realized as floorplan
+#[derive(Debug, Clone, Copy)]
+pub enum Realization {
+    MinArea(),
+    MinWire(f64), // relative area tolerance w.r.t. the minimum area shape
+}
+
+impl From<String> for Realization {
+    fn from(str: String) -> Self {
+        if str == "min_area" {
+            Realization::MinArea()
+        }
+        else if str == "min_wire" {
+            Realization::MinWire(0.0)
+        }
+        else {
+            panic!("unsupported realization type")
+        }
+    }
+}
+
+impl Realization {
+    pub fn set_area_tolerance(&mut self, tolerance: f64) {
+        if let Realization::MinWire(_) = self {
+            *self = Realization::MinWire(tolerance);
+        }
+    }
+}
+
+impl Default for Realization {
+    fn default() -> Self {
+        Self::MinArea()
+    }
+}
+
 #[derive(Debug, Clone, Default)]
 struct SlicingTreeNode {
     left: usize,
@@ -155,9 +190,14 @@ impl SlicingTree {
     
     // (origin x, origin y, (width, height), ModuleNode)
     pub fn recompute_floorplan(&mut self) {
+        self.recompute_floorplan_with_root(self.get_bounding_box());
+    }
+
+    // realizes the floorplan for a given point of the root shape function
+    pub fn recompute_floorplan_with_root(&mut self, root_shape: Rectangle) {
         let mut v: usize = self.root;
         self.stack.push(v);
-        self.node_placement[v] = (0, 0, self.get_bounding_box(), self.nodes[v].module_type);
+        self.node_placement[v] = (0, 0, root_shape, self.nodes[v].module_type);
         while self.stack.len() > 0 {
             v = self.stack.pop().unwrap();
             let l = self.nodes[v].left;
@@ -207,6 +247,16 @@ impl SlicingTree {
         self.get_bounding_box().area() as f64
     }
 
+    /// root shapes with area at most (1 + tolerance) times the minimum area
+    pub fn get_near_minimal_shapes(&self, tolerance: f64) -> Vec<Rectangle> {
+        let bound = self.get_min_area() * (1.0 + tolerance);
+        self.nodes[self.root].shape.points
+            .iter()
+            .filter(|r| r.area() as f64 <= bound)
+            .copied()
+            .collect()
+    }
+
     pub fn mark_path(&mut self, v: usize) {
         let mut w = v;
         while w != NO_PARENT {
@@ -0,0 +1,427 @@
+use crate::definitions::*;
+use crate::floorplan_common::*;
+use crate::crossover::*;
+use rand::prelude::*;
+
+// relation of an ordered pair (i, j) in the closure graphs
+const NONE: u8 = 0;
+const LEFT: u8 = 1;  // edge (i, j) in the horizontal graph Ch, i is left of j
+const BELOW: u8 = 2; // edge (i, j) in the vertical graph Cv, i is below j
+
+// relation matrix of both closure graphs, rotated rectangles
+pub type TCGSolution = (Vec<u8>, Vec<Rectangle>);
+
+#[derive(Debug)]
+pub enum TCGMoveType {
+    RotateModule(usize),
+    SwapModules(usize, usize),
+    ReverseEdge(usize, usize), // reduction edge (i, j) becomes (j, i) in the same graph
+    MoveEdge(usize, usize),    // reduction edge (i, j) is moved to the other graph
+}
+
+// every pair of modules is related in exactly one of Ch and Cv and no module is related to itself
+fn is_total(relation: &[u8], n: usize) -> bool {
+    (0..n).all(|i| (0..n).all(|j| {
+        let related = (relation[i * n + j] != NONE) as usize + (relation[j * n + i] != NONE) as usize;
+        related == (i != j) as usize
+    }))
+}
+
+/// Transitive closure graph representation (Lin, Chang)
+/// Both closure graphs are kept as one relation matrix, every pair of modules is related in exactly one graph.
+/// The moves work on the graphs and add the edges implied by transitivity,
+/// packing computes longest paths in O(n^2), which is the size of the closure graphs.
+#[derive(Debug, Default)]
+pub struct TransitiveClosureGraph {
+    modules: Vec<Rectangle>,
+    nets: Vec<Net>,
+    relation: Vec<u8>,            // relation[i * n + j]
+    changes: Vec<(usize, u8)>,    // previous entries of the relation changed by the last edge move
+    order: Vec<Int>,              // topological order of both graphs
+    placement: Floorplan,
+
+    cost_function: CostFunction,
+    current_cost: f64,
+    current_wire: f64,
+    current_area: f64,
+    bounding_box: Rectangle,
+}
+
+impl TransitiveClosureGraph {
+    pub fn new(modules: Vec<Rectangle>, nets: Vec<Net>, alpha: f64) -> Self {
+        let n = modules.len();
+        // initial graphs: Ch is a total order
+        let sequence: Vec<Int> = (0..n).collect();
+        let mut tcg = TransitiveClosureGraph {
+            placement: vec![(0, 0, Rectangle::new(0, 0)); n],
+            modules,
+            nets,
+            relation: Self::from_sequences(&sequence, &sequence),
+            order: vec![0; n],
+            cost_function: CostFunction::new(alpha, 1.0, 1.0),
+            ..Default::default()
+        };
+
+        // update cost parameter
+        tcg.update();
+        tcg.update_cost_function();
+
+        tcg
+    }
+
+    pub fn update(&mut self) {
+        self.compute_floorplan();
+        self.current_area = self.bounding_box.area() as f64;
+        self.current_wire = CostFunction::compute_wirelength(&self.placement, &self.nets);
+        self.current_cost = self.cost_function.get_cost(self.current_area, self.current_wire, &self.placement);
+    }
+
+    pub fn update_cost_function(&mut self) {
+        // compute averages for cost function
+        let repetitions = 3 * self.modules.len();
+        let samples = sample_perturbations(self, repetitions, self.cost_function.has_terms());
+        self.cost_function.update_averages(&samples);
+        // perturbations are reversed without update
+        self.update();
+    }
+
+    /// registers additional weighted cost terms and re-estimates their averages
+    pub fn set_cost_terms(&mut self, terms: Vec<WeightedCostTerm>) {
+        self.cost_function.set_terms(terms);
+        self.update_cost_function();
+    }
+
+    /// relation of the TCG equivalent to a sequence pair
+    /// i left of j: i before j in both sequences
+    /// i below j: i after j in the x sequence and before j in the y sequence
+    pub fn from_sequences(x_sequence: &[Int], y_sequence: &[Int]) -> Vec<u8> {
+        let n = x_sequence.len();
+        let mut index_x = vec![0; n];
+        let mut index_y = vec![0; n];
+        for (pos, &id) in x_sequence.iter().enumerate() {
+            index_x[id] = pos;
+        }
+        for (pos, &id) in y_sequence.iter().enumerate() {
+            index_y[id] = pos;
+        }
+        let mut relation = vec![NONE; n * n];
+        for i in 0..n {
+            for j in 0..n {
+                if index_y[i] < index_y[j] {
+                    relation[i * n + j] = if index_x[i] < index_x[j] { LEFT } else { BELOW };
+                }
+            }
+        }
+        relation
+    }
+
+    /// sequence pair equivalent to the relation, inverse of from_sequences
+    pub fn to_sequences(relation: &[u8]) -> (Vec<Int>, Vec<Int>) {
+        let n = (relation.len() as f64).sqrt().round() as usize;
+        let mut x_sequence = vec![0; n];
+        let mut y_sequence = vec![0; n];
+        for j in 0..n {
+            // both orders are transitive tournaments, the rank is the number of predecessors
+            let rank_x = (0..n).filter(|&i| relation[i * n + j] == LEFT || relation[j * n + i] == BELOW).count();
+            let rank_y = (0..n).filter(|&i| relation[i * n + j] != NONE).count();
+            x_sequence[rank_x] = j;
+            y_sequence[rank_y] = j;
+        }
+        (x_sequence, y_sequence)
+    }
+
+    /// longest paths in Ch and Cv in a topological order of both graphs
+    pub fn compute_floorplan(&mut self) {
+        let n = self.modules.len();
+        // every pair is related in one graph, so the number of predecessors is the position in the order
+        debug_assert!(is_total(&self.relation, n));
+        for j in 0..n {
+            let rank = (0..n).filter(|&i| self.relation[i * n + j] != NONE).count();
+            self.order[rank] = j;
+        }
+        for pos in 0..n {
+            let j = self.order[pos];
+            let (mut x, mut y) = (0, 0);
+            for &i in self.order[..pos].iter() {
+                let (xi, yi, rect) = self.placement[i];
+                match self.relation[i * n + j] {
+                    LEFT => x = x.max(xi + rect.width),
+                    BELOW => y = y.max(yi + rect.height),
+                    _ => (),
+                }
+            }
+            self.placement[j] = (x, y, self.modules[j]);
+        }
+
+        // compute bounding box
+        self.bounding_box.width = self.placement.iter().map(|(x, _, r)| x + r.width).max().unwrap();
+        self.bounding_box.height = self.placement.iter().map(|(_, y, r)| y + r.height).max().unwrap();
+    }
+
+    // changes one entry of the relation and records its previous value
+    fn set_relation(&mut self, i: usize, j: usize, edge_type: u8) {
+        let index = i * self.modules.len() + j;
+        self.changes.push((index, self.relation[index]));
+        self.relation[index] = edge_type;
+    }
+
+    // a random successor of i, walked back to a reduction edge (i, j) in one scan over the successors:
+    // a successor k in front of the current j replaces it, a later k in front of the final j would be in front of the replaced one
+    fn reduction_edge(&self, i: usize, edge_type: u8, rng: &mut ThreadRng) -> Option<usize> {
+        let n = self.modules.len();
+        let successors: Vec<usize> = (0..n).filter(|&j| self.relation[i * n + j] == edge_type).collect();
+        let mut j = *successors.choose(rng)?;
+        for &k in successors.iter() {
+            if self.relation[k * n + j] == edge_type {
+                j = k;
+            }
+        }
+        Some(j)
+    }
+
+    // exchanges the nodes of a and b in both graphs, is its own inverse
+    fn swap_nodes(&mut self, a: usize, b: usize) {
+        let n = self.modules.len();
+        for k in 0..n {
+            self.relation.swap(a * n + k, b * n + k);
+        }
+        for k in 0..n {
+            self.relation.swap(k * n + a, k * n + b);
+        }
+    }
+
+    // after inserting the edge (a, b) every predecessor of a (and a) has to be in front of
+    // every successor of b (and b) in the graph, such pairs are removed from the other graph
+    fn close_edge(&mut self, a: usize, b: usize, edge_type: u8) {
+        let n = self.modules.len();
+        let predecessors: Vec<usize> = (0..n).filter(|&k| k == a || self.relation[k * n + a] == edge_type).collect();
+        let successors: Vec<usize> = (0..n).filter(|&l| l == b || self.relation[b * n + l] == edge_type).collect();
+        for &k in predecessors.iter() {
+            for &l in successors.iter() {
+                if self.relation[k * n + l] != edge_type {
+                    self.set_relation(k, l, edge_type);
+                    if self.relation[l * n + k] != NONE {
+                        self.set_relation(l, k, NONE);
+                    }
+                }
+            }
+        }
+    }
+
+    fn reverse_edge(&mut self, i: usize, j: usize) {
+        let edge_type = self.relation[i * self.modules.len() + j];
+        self.set_relation(i, j, NONE);
+        self.set_relation(j, i, edge_type);
+        self.close_edge(j, i, edge_type);
+    }
+
+    fn move_edge(&mut self, i: usize, j: usize) {
+        let edge_type = LEFT + BELOW - self.relation[i * self.modules.len() + j];
+        self.set_relation(i, j, edge_type);
+        self.close_edge(i, j, edge_type);
+    }
+}
+
+impl Mutation<TCGMoveType> for TransitiveClosureGraph {
+    fn get_random_move(&mut self) -> TCGMoveType {
+        let mut rng: ThreadRng = rand::thread_rng();
+        let n = self.modules.len();
+        let r = if n < 2 { 0 } else { rng.gen_range(0..4) };
+        let a = rng.gen_range(0..n);
+        let b = if n < 2 { a } else { (a + rng.gen_range(1..n)) % n };
+        let move_type: TCGMoveType = match r {
+            0 => TCGMoveType::RotateModule(a),
+            1 => TCGMoveType::SwapModules(a, b),
+            _ => {
+                let edge_type = if rng.gen_bool(0.5) { LEFT } else { BELOW };
+                match self.reduction_edge(a, edge_type, &mut rng) {
+                    // sinks of a graph have no outgoing edges
+                    None => TCGMoveType::SwapModules(a, b),
+                    Some(j) if r == 2 => TCGMoveType::ReverseEdge(a, j),
+                    Some(j) => TCGMoveType::MoveEdge(a, j),
+                }
+            }
+        };
+        move_type
+    }
+
+    // update = false reverses the previously applied move
+    fn apply_move(&mut self, _move: &TCGMoveType, update: bool) {
+        match *_move {
+            TCGMoveType::RotateModule(a) => self.modules[a] = self.modules[a].transpose(),
+            TCGMoveType::SwapModules(a, b) => self.swap_nodes(a, b),
+            _ if update => {
+                self.changes.clear();
+                match *_move {
+                    TCGMoveType::ReverseEdge(i, j) => self.reverse_edge(i, j),
+                    TCGMoveType::MoveEdge(i, j) => self.move_edge(i, j),
+                    _ => unreachable!(),
+                }
+            }
+            _ => {
+                while let Some((index, edge_type)) = self.changes.pop() {
+                    self.relation[index] = edge_type;
+                }
+            }
+        }
+        if update {
+            self.update()
+        }
+    }
+}
+
+impl FloorCost for TransitiveClosureGraph {
+    fn get_floor_area(&self) -> f64 {
+        self.current_area
+    }
+
+    fn get_floor_wire(&self) -> f64 {
+        self.current_wire
+    }
+}
+
+impl FloorPlan for TransitiveClosureGraph {
+    fn get_floorplan(&self) -> Floorplan {
+        self.placement.clone()
+    }
+}
+
+impl Cost for TransitiveClosureGraph {
+    fn get_cost(&self) -> f64 {
+        self.current_cost
+    }
+}
+
+impl Solution<TCGSolution> for TransitiveClosureGraph {
+    fn copy_solution(&self) -> TCGSolution {
+        (self.relation.clone(), self.modules.clone())
+    }
+
+    fn set_solution(&mut self, solution: TCGSolution) {
+        (self.relation, self.modules) = solution;
+        self.update()
+    }
+}
+
+impl RandomSolution<TCGSolution> for TransitiveClosureGraph {
+    // graphs of a random sequence pair
+    fn random_solution(&self) -> TCGSolution {
+        let mut rng: ThreadRng = rand::thread_rng();
+        let mut x_sequence: Vec<Int> = (0..self.modules.len()).collect();
+        let mut y_sequence: Vec<Int> = x_sequence.clone();
+        x_sequence.shuffle(&mut rng);
+        y_sequence.shuffle(&mut rng);
+        let mut rect = self.modules.clone();
+        for r in rect.iter_mut() {
+            if rng.gen_bool(0.5) {
+                *r = r.transpose();
+            }
+        }
+        (Self::from_sequences(&x_sequence, &y_sequence), rect)
+    }
+}
+
+impl Crossover<TCGSolution> for TransitiveClosureGraph {
+    // order crossover of the equivalent sequence pairs
+    fn crossover(&self, a: &TCGSolution, b: &TCGSolution) -> TCGSolution {
+        let (x_a, y_a) = Self::to_sequences(&a.0);
+        let (x_b, y_b) = Self::to_sequences(&b.0);
+        let cut = random_cut(x_a.len());
+        let (x_sequence, from_a) = order_crossover(&x_a, &x_b, cut);
+        let (y_sequence, _) = order_crossover(&y_a, &y_b, cut);
+        let rect: Vec<Rectangle> = (0..from_a.len()).map(|i| if from_a[i] { a.1[i] } else { b.1[i] }).collect();
+        (Self::from_sequences(&x_sequence, &y_sequence), rect)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compaction::find_overlaps;
+
+    // every pair is related in exactly one graph and both graphs are transitive
+    fn is_valid(relation: &[u8], n: usize) -> bool {
+        if !is_total(relation, n) {
+            return false;
+        }
+        for i in 0..n {
+            for j in 0..n {
+                for k in 0..n {
+                    let edge_type = relation[i * n + j];
+                    if edge_type != NONE && relation[j * n + k] == edge_type && relation[i * n + k] != edge_type {
+                        return false;
+                    }
+                }
+            }
+        }
+        true
+    }
+
+    #[test]
+    fn test_sequences() {
+        // 0 left of 1, 2 below 0 and 1
+        let (x_sequence, y_sequence) = (vec![0, 1, 2], vec![2, 0, 1]);
+        let relation = TransitiveClosureGraph::from_sequences(&x_sequence, &y_sequence);
+        assert_eq!(relation, vec![
+            NONE, LEFT, NONE,
+            NONE, NONE, NONE,
+            BELOW, BELOW, NONE,
+        ]);
+        assert_eq!(TransitiveClosureGraph::to_sequences(&relation), (x_sequence, y_sequence));
+        let mut tcg = TransitiveClosureGraph::new(vec![Rectangle::new(2, 1), Rectangle::new(1, 3), Rectangle::new(4, 2)], Vec::new(), 1.0);
+        tcg.set_solution((relation, tcg.modules.clone()));
+        assert_eq!(tcg.get_floorplan(), vec![(0, 2, Rectangle::new(2, 1)), (2, 2, Rectangle::new(1, 3)), (0, 0, Rectangle::new(4, 2))]);
+    }
+
+    #[test]
+    fn test_moves() {
+        let n = 12;
+        let mut rng = StdRng::seed_from_u64(0);
+        let modules: Vec<Rectangle> = (0..n).map(|_| Rectangle::new(rng.gen_range(1..10), rng.gen_range(1..10))).collect();
+        let mut tcg = TransitiveClosureGraph::new(modules, Vec::new(), 1.0);
+        for _ in 0..500 {
+            let _move = tcg.get_random_move();
+            let solution = tcg.copy_solution();
+            tcg.apply_move(&_move, true);
+            assert!(is_valid(&tcg.relation, n), "{:?}", _move);
+            assert!(find_overlaps(&tcg.get_floorplan()).is_empty());
+            tcg.apply_move(&_move, false);
+            assert_eq!(tcg.copy_solution(), solution);
+            // keep the move to walk through different graphs
+            tcg.apply_move(&_move, true);
+        }
+    }
+
+    #[test]
+    fn test_every_move() {
+        // each move on every module and reduction edge keeps every pair related in exactly one graph
+        let n = 8;
+        let mut rng = StdRng::seed_from_u64(1);
+        let modules: Vec<Rectangle> = (0..n).map(|_| Rectangle::new(rng.gen_range(1..10), rng.gen_range(1..10))).collect();
+        let mut tcg = TransitiveClosureGraph::new(modules, Vec::new(), 1.0);
+        let (x_sequence, y_sequence) = (vec![3, 0, 6, 1, 7, 4, 2, 5], vec![2, 6, 0, 5, 3, 7, 1, 4]);
+        tcg.set_solution((TransitiveClosureGraph::from_sequences(&x_sequence, &y_sequence), tcg.modules.clone()));
+        let mut thread_rng = rand::thread_rng();
+        let mut moves: Vec<TCGMoveType> = Vec::new();
+        for a in 0..n {
+            moves.push(TCGMoveType::RotateModule(a));
+            moves.push(TCGMoveType::SwapModules(a, (a + 3) % n));
+            for edge_type in [LEFT, BELOW] {
+                if let Some(j) = tcg.reduction_edge(a, edge_type, &mut thread_rng) {
+                    moves.push(TCGMoveType::ReverseEdge(a, j));
+                    moves.push(TCGMoveType::MoveEdge(a, j));
+                }
+            }
+        }
+        assert!(moves.iter().any(|m| matches!(m, TCGMoveType::ReverseEdge(_, _))));
+        assert!(moves.iter().any(|m| matches!(m, TCGMoveType::MoveEdge(_, _))));
+        let solution = tcg.copy_solution();
+        for _move in moves.iter() {
+            tcg.apply_move(_move, true);
+            assert!(is_total(&tcg.relation, n), "{:?}", _move);
+            assert!(is_valid(&tcg.relation, n), "{:?}", _move);
+            tcg.apply_move(_move, false);
+            assert_eq!(tcg.copy_solution(), solution);
+        }
+    }
+}